@@ -0,0 +1,78 @@
+//! Compare rolling the dice one by one with `gen_range`, as before `DiceRollSource::roll_many()`,
+//! with rolling them in batch, as `Roller::roll()` and `Roller::roll_with()` do.
+//!
+//! Run with `cargo bench --bench roll_many`. Results in release on a x86_64 Linux machine,
+//! `roll_with()` being given a `StdRng`:
+//!
+//! | Rolled                       | Method        | Before    | After     |
+//! |------------------------------|---------------|-----------|-----------|
+//! | `5000d5000`                  | `roll()`      | 661 µs    | 567 µs    |
+//! | `5000d5000`                  | `roll_with()` | 645 µs    | 547 µs    |
+//! | `(20d6 ie6 + 10d10 K5)^1000` | `roll()`      | 6.96 ms   | 6.84 ms   |
+//! | `(20d6 ie6 + 10d10 K5)^1000` | `roll_with()` | 6.14 ms   | 6.06 ms   |
+//! | `1000000d20`                 | `roll()`      | 142 ms    | 113 ms    |
+//! | `1000000d20`                 | `roll_with()` | 123 ms    | 111 ms    |
+
+use std::time::{Duration, Instant};
+
+use caith::{DiceRollSource, Roller, RollerLimits};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const ITERATIONS: u32 = 200;
+
+// The dice as they were rolled before `roll_many`: one by one, with `gen_range`
+struct OneByOne<R: Rng>(R);
+
+impl<R: Rng> DiceRollSource for OneByOne<R> {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        self.0.gen_range(1..1 + sides)
+    }
+}
+
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    // warm up
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn compare_roll(roller: &Roller, name: &str) {
+    let mut source = OneByOne(rand::thread_rng());
+    let one_by_one = measure(|| {
+        roller.roll_with_source(&mut source).unwrap();
+    });
+    let batch = measure(|| {
+        roller.roll().unwrap();
+    });
+    println!("roll() {} before: {:?}", name, one_by_one);
+    println!("roll() {} after:  {:?}", name, batch);
+}
+
+fn compare_roll_with(roller: &Roller, name: &str) {
+    let mut source = OneByOne(StdRng::seed_from_u64(42));
+    let one_by_one = measure(|| {
+        roller.roll_with_source(&mut source).unwrap();
+    });
+    let mut rng = StdRng::seed_from_u64(42);
+    let batch = measure(|| {
+        roller.roll_with(&mut rng).unwrap();
+    });
+    println!("roll_with() {} before: {:?}", name, one_by_one);
+    println!("roll_with() {} after:  {:?}", name, batch);
+}
+
+fn main() {
+    let limits = RollerLimits {
+        max_dice: 1_000_000,
+        max_total_dice: 1_000_000,
+        ..Default::default()
+    };
+    for expr in &["5000d5000", "(20d6 ie6 + 10d10 K5)^1000", "1000000d20"] {
+        let roller = Roller::new(expr).unwrap().with_limits(limits);
+        compare_roll(&roller, expr);
+        compare_roll_with(&roller, expr);
+    }
+}
@@ -12,8 +12,11 @@ use ::mice::parse::parse_expression;
 fuzz_target!(|data: &str| {
     if let Ok((rest, _)) = parse_expression(data.as_bytes()) {
         if rest.is_empty() {
+            // a valid expression must never make the roller panic
             if let Ok(roller) = Roller::new(data) {
-                let _ = roller.roll();
+                if let Err(e) = roller.roll() {
+                    let _ = e.to_string();
+                }
             }
         }
     }
@@ -1,11 +1,38 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
 
-use ::caith::Roller;
-use ::mice::parse::parse_expression;
+use ::caith::{Roller, RollerLimits};
 
-fuzz_target!(|data: &str| {
+// Rolling must never panic: any input gives either a result or an error pointing inside the input.
+fn check_roll(data: &str, limits: RollerLimits) {
     if let Ok(roller) = Roller::new(data) {
-        let _ = roller.roll();
+        match roller.with_limits(limits).roll() {
+            Ok(res) => {
+                let _ = res.to_string();
+            }
+            Err(e) => {
+                if let Some(span) = e.span() {
+                    assert!(span.start <= span.end && span.end <= data.len());
+                }
+                let _ = e.to_string();
+            }
+        }
     }
+}
+
+fuzz_target!(|data: &str| {
+    check_roll(data, RollerLimits::default());
+    // tight limits to exercise the truncation paths
+    check_roll(
+        data,
+        RollerLimits {
+            max_dice: 10,
+            max_sides: 10,
+            max_explosions: 2,
+            max_rerolls: 2,
+            max_repetitions: 3,
+            max_total_dice: 20,
+            max_depth: 4,
+        },
+    );
 });
@@ -0,0 +1,861 @@
+use std::fmt::Display;
+
+use pest::iterators::{Pair, Pairs};
+
+use crate::{
+    distribution::{dice_mean, AnalysisContext},
+    error::{Result, RollError, Span},
+    parser::{self, Rule},
+    AnalysisOptions, FloatRounding, Rational, Roller, RollerLimits,
+};
+
+// The analysis must stay cheap, the average of a bigger expression is not computed
+const MAX_OUTCOMES: u64 = 100_000;
+
+/// What the total of an expression counts, see [`Analysis::get_kind()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TotalKind {
+    /// The total is a sum of dice and numbers
+    Sum,
+    /// Every dice counts successes with `t`, `tt` or `f`
+    Successes,
+    /// Some dice count successes and others are summed
+    Mixed,
+}
+
+/// What can be known about an expression without rolling it, see [`Roller::analyze()`].
+///
+/// `Display` gives the bounds and the average, like `3–18, avg 10.5`, or the total when there is
+/// only one.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{Roller, TotalKind};
+///
+/// let analysis = Roller::new("3d6").unwrap().analyze().unwrap();
+/// assert_eq!(3, analysis.get_min());
+/// assert_eq!(18, analysis.get_max());
+/// assert_eq!(Some(10.5), analysis.get_average());
+/// assert_eq!(TotalKind::Sum, analysis.get_kind());
+/// assert_eq!("3–18, avg 10.5", analysis.to_string());
+///
+/// // each die can explode 100 times with the default limits
+/// let analysis = Roller::new("2d6 ie6").unwrap().analyze().unwrap();
+/// assert_eq!(202, analysis.get_max_dice());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    min: i64,
+    max: i64,
+    average: Option<f64>,
+    kind: TotalKind,
+    max_dice: u64,
+}
+
+impl Analysis {
+    /// Get the lowest possible total. For a repetition not summed with `^+`, it's the one of each
+    /// roll.
+    pub fn get_min(&self) -> i64 {
+        self.min
+    }
+
+    /// Get the highest possible total. For a repetition not summed with `^+`, it's the one of each
+    /// roll.
+    pub fn get_max(&self) -> i64 {
+        self.max
+    }
+
+    /// Get the expected total, `None` if it can't be computed without rolling, see
+    /// [`Roller::distribution()`]. For a repetition not summed with `^+`, it's the one of each
+    /// roll.
+    pub fn get_average(&self) -> Option<f64> {
+        self.average
+    }
+
+    /// Get what the total counts
+    pub fn get_kind(&self) -> TotalKind {
+        self.kind
+    }
+
+    /// Get the highest number of dice the expression can roll, explosions, rerolls and repetitions
+    /// included, within the limits of the roller.
+    pub fn get_max_dice(&self) -> u64 {
+        self.max_dice
+    }
+}
+
+impl Display for Analysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            // the average is the total itself
+            return write!(f, "{}", self.min);
+        }
+        write!(f, "{}–{}", self.min, self.max)?;
+        if let Some(average) = self.average {
+            let average = format!("{:.2}", average);
+            let average = average.trim_end_matches('0').trim_end_matches('.');
+            write!(f, ", avg {}", average)?;
+        }
+        Ok(())
+    }
+}
+
+// What is known about a part of the expression
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    min: i64,
+    max: i64,
+    mean: Option<f64>,
+    // `None` for numbers
+    kind: Option<TotalKind>,
+    max_dice: u64,
+}
+
+impl Bounds {
+    fn constant(total: i64) -> Self {
+        Bounds {
+            min: total,
+            max: total,
+            mean: Some(total as f64),
+            kind: None,
+            max_dice: 0,
+        }
+    }
+
+    fn from_candidates<I: Iterator<Item = i64>>(candidates: I, lhs: &Self, rhs: &Self) -> Self {
+        let (min, max) = candidates.fold((i64::MAX, i64::MIN), |(min, max), c| {
+            (min.min(c), max.max(c))
+        });
+        Bounds {
+            min,
+            max,
+            mean: None,
+            kind: combine_kinds(lhs.kind, rhs.kind),
+            max_dice: lhs.max_dice.saturating_add(rhs.max_dice),
+        }
+    }
+}
+
+fn combine_kinds(lhs: Option<TotalKind>, rhs: Option<TotalKind>) -> Option<TotalKind> {
+    match (lhs, rhs) {
+        (None, kind) | (kind, None) => kind,
+        (Some(lhs), Some(rhs)) if lhs == rhs => Some(lhs),
+        _ => Some(TotalKind::Mixed),
+    }
+}
+
+// What is known about a part of the expression involving a fraction, which keeps its exact value
+// like in the roller, the total being rounded at the end
+#[derive(Debug, Clone, Copy)]
+struct ExactBounds {
+    min: Rational,
+    max: Rational,
+    kind: Option<TotalKind>,
+    max_dice: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Int(Bounds),
+    Exact(ExactBounds),
+}
+
+impl Operand {
+    fn into_bounds(self, rounding: FloatRounding) -> Bounds {
+        match self {
+            Operand::Int(bounds) => bounds,
+            Operand::Exact(b) if b.min == b.max => Bounds {
+                kind: b.kind,
+                max_dice: b.max_dice,
+                ..Bounds::constant(b.min.round(rounding))
+            },
+            Operand::Exact(b) => Bounds {
+                min: b.min.round(rounding),
+                max: b.max.round(rounding),
+                mean: None,
+                kind: b.kind,
+                max_dice: b.max_dice,
+            },
+        }
+    }
+
+    fn is_maybe_zero(&self) -> bool {
+        let (min, max) = self.ends();
+        min.numer() <= 0 && 0 <= max.numer()
+    }
+
+    fn ends(&self) -> (Rational, Rational) {
+        match self {
+            Operand::Int(b) => (b.min.into(), b.max.into()),
+            Operand::Exact(b) => (b.min, b.max),
+        }
+    }
+
+    fn kind(&self) -> Option<TotalKind> {
+        match self {
+            Operand::Int(b) => b.kind,
+            Operand::Exact(b) => b.kind,
+        }
+    }
+
+    fn max_dice(&self) -> u64 {
+        match self {
+            Operand::Int(b) => b.max_dice,
+            Operand::Exact(b) => b.max_dice,
+        }
+    }
+}
+
+// The bounds of an exact operation, extreme at the ends of the operands as long as the divisor
+// doesn't change sign. A single value which doesn't fit always overflows, otherwise the ends
+// saturate where the roller would overflow.
+fn exact_bounds(
+    lhs: &Operand,
+    rhs: &Operand,
+    exact_op: fn(Rational, Rational) -> Option<Rational>,
+    float_op: fn(f64, f64) -> f64,
+    span: Span,
+) -> Result<Operand> {
+    let ((lmin, lmax), (rmin, rmax)) = (lhs.ends(), rhs.ends());
+    let mut values = Vec::with_capacity(4);
+    for &l in [lmin, lmax].iter() {
+        for &r in [rmin, rmax].iter() {
+            values.push(match exact_op(l, r) {
+                Some(v) => v,
+                None if lmin == lmax && rmin == rmax => return Err(RollError::Overflow { span }),
+                None if float_op(l.to_f64(), r.to_f64()) < 0.0 => i64::MIN.into(),
+                None => i64::MAX.into(),
+            });
+        }
+    }
+    Ok(Operand::Exact(ExactBounds {
+        min: *values.iter().min().unwrap(),
+        max: *values.iter().max().unwrap(),
+        kind: combine_kinds(lhs.kind(), rhs.kind()),
+        max_dice: lhs.max_dice().saturating_add(rhs.max_dice()),
+    }))
+}
+
+// The total of the dice options, like `TotalModifier`
+enum Modifier {
+    Sum,
+    Keep(u64),
+    Drop(u64),
+    Targets(u64, u64, u64),
+    TargetEnum(Vec<u64>),
+}
+
+// Count of dice, as an interval
+type Count = (u64, u64);
+
+fn add_count(lhs: Count, rhs: Count) -> Count {
+    (lhs.0.saturating_add(rhs.0), lhs.1.saturating_add(rhs.1))
+}
+
+// Like `compute_option`, no dice is dropped if there are not enough dice
+fn drop_count((low, high): Count, n: u64) -> Count {
+    let new_low = if low >= n {
+        low - n
+    } else if high >= n {
+        0
+    } else {
+        low
+    };
+    let new_high = if high < n {
+        high
+    } else if low < n {
+        (high - n).max(n - 1)
+    } else {
+        high - n
+    };
+    (new_low, new_high)
+}
+
+// Reject a dice expression the roller always rejects, like too many dice or sides
+pub(crate) fn check_dice(
+    dice: Pair<Rule>,
+    limits: &RollerLimits,
+    ctx: &AnalysisContext,
+) -> Result<()> {
+    dice_bounds(dice, limits, ctx).map(|_| ())
+}
+
+// Follows `parser::compute_roll`, with the number of dice in the roll and in the history as
+// intervals
+fn dice_bounds(dice: Pair<Rule>, limits: &RollerLimits, ctx: &AnalysisContext) -> Result<Bounds> {
+    let span: Span = dice.as_span().into();
+    let mut inner = dice.clone().into_inner();
+    let first = inner.next().unwrap();
+    let nb: u64 = match first.as_rule() {
+        Rule::nb_dice => {
+            inner.next(); // skip `d` token
+            let nb = parser::parse_number(&first)?;
+            if nb > limits.max_dice {
+                return Err(RollError::TooManyDice {
+                    span: first.as_span().into(),
+                    requested: nb,
+                    max: limits.max_dice,
+                });
+            }
+            nb
+        }
+        _ => 1,
+    };
+    let sides_pair = inner.next().unwrap();
+    if sides_pair.as_rule() == Rule::fudge {
+        return Ok(Bounds {
+            min: -(nb as i64),
+            max: nb as i64,
+            mean: Some(0.0),
+            kind: Some(TotalKind::Sum),
+            max_dice: nb,
+        });
+    }
+    let sides: u64 = parser::parse_number(&sides_pair)?;
+    if sides == 0 {
+        return Err(RollError::ZeroSides {
+            span: sides_pair.as_span().into(),
+        });
+    } else if sides > limits.max_sides {
+        return Err(RollError::TooManySides {
+            span: sides_pair.as_span().into(),
+            requested: sides,
+            max: limits.max_sides,
+        });
+    }
+
+    let mut res = (nb, nb);
+    let mut flat = (0, 0);
+    let mut max_dice = nb;
+    let mut history_empty = true;
+    let mut modifier = Modifier::Sum;
+    let mut prev_explode = false;
+    let mut has_options = false;
+    for option in inner {
+        has_options = true;
+        let rule = option.as_rule();
+        let value = || parser::extract_option_value(option.clone());
+        match rule {
+            Rule::explode | Rule::i_explode => {
+                let value = value()?.unwrap_or(sides);
+                let (all, some) = (value <= 1, value <= sides);
+                if !prev_explode {
+                    flat = add_count(flat, res);
+                }
+                let depth = if rule == Rule::i_explode {
+                    limits.max_explosions
+                } else {
+                    limits.max_explosions.min(1)
+                };
+                if some && depth > 0 {
+                    // each level of explosions rolls at most as many dice as the previous one
+                    let level = (if all { res.0 } else { 0 }, res.1);
+                    let exploded = (level.0.saturating_mul(depth), level.1.saturating_mul(depth));
+                    flat = add_count(flat, exploded);
+                    max_dice = max_dice.saturating_add(exploded.1);
+                }
+                res = if rule == Rule::i_explode {
+                    if some && depth > 0 {
+                        (if all { res.0 } else { 0 }, res.1)
+                    } else {
+                        (0, 0)
+                    }
+                } else if all {
+                    res
+                } else {
+                    (res.0.min(1), res.1)
+                };
+                modifier = Modifier::Sum;
+            }
+            Rule::reroll | Rule::i_reroll => {
+                if value()?.unwrap() >= 1 {
+                    let depth = if rule == Rule::i_reroll {
+                        limits.max_rerolls
+                    } else {
+                        limits.max_rerolls.min(1)
+                    };
+                    max_dice = max_dice.saturating_add(res.1.saturating_mul(depth));
+                }
+                flat = add_count(flat, res);
+                modifier = Modifier::Sum;
+            }
+            Rule::keep_hi | Rule::keep_lo | Rule::drop_hi | Rule::drop_lo => {
+                let n = value()?.unwrap();
+                if history_empty {
+                    flat = add_count(flat, res);
+                }
+                if rule == Rule::keep_hi || rule == Rule::keep_lo {
+                    res = (res.0.min(n), res.1.min(n));
+                    modifier = Modifier::Keep(n);
+                } else {
+                    res = drop_count(res, n);
+                    modifier = Modifier::Drop(n);
+                }
+            }
+            Rule::target | Rule::double_target | Rule::failure => {
+                let value_or_enum = option.clone().into_inner().next().unwrap();
+                if value_or_enum.as_rule() == Rule::target_enum {
+                    let list = value_or_enum
+                        .into_inner()
+                        .map(|p| parser::parse_number(&p))
+                        .collect::<Result<Vec<_>>>()?;
+                    flat = add_count(flat, res);
+                    modifier = Modifier::TargetEnum(list);
+                } else {
+                    let value = parser::parse_number(&value_or_enum)?;
+                    let (t, f, d) = match rule {
+                        Rule::target => (value, 0, 0),
+                        Rule::failure => (0, value, 0),
+                        _ => (0, 0, value),
+                    };
+                    modifier = match modifier {
+                        Modifier::Targets(ot, of, od) => {
+                            if t > 0 {
+                                Modifier::Targets(t, of, od)
+                            } else if f > 0 {
+                                Modifier::Targets(ot, f, od)
+                            } else {
+                                Modifier::Targets(ot, of, d)
+                            }
+                        }
+                        _ => {
+                            flat = add_count(flat, res);
+                            Modifier::Targets(t, f, d)
+                        }
+                    };
+                }
+            }
+            _ => unreachable!("{:#?}", option),
+        }
+        history_empty = false;
+        prev_explode = rule == Rule::explode || rule == Rule::i_explode;
+    }
+    if !has_options {
+        flat = res;
+    }
+
+    let not_enough = |n: u64| RollError::NotEnoughDice {
+        span,
+        requested: n as usize,
+        available: flat.1 as usize,
+    };
+    let sides_i = sides as i64;
+    let (min, max, kind) = match modifier {
+        Modifier::Sum => (
+            flat.0 as i64,
+            (flat.1 as i64).saturating_mul(sides_i),
+            TotalKind::Sum,
+        ),
+        Modifier::Keep(n) if n > flat.1 => return Err(not_enough(n)),
+        Modifier::Keep(n) => (n as i64, (n as i64).saturating_mul(sides_i), TotalKind::Sum),
+        Modifier::Drop(n) if n > flat.1 => return Err(not_enough(n)),
+        Modifier::Drop(n) => (
+            flat.0.saturating_sub(n) as i64,
+            ((flat.1 - n) as i64).saturating_mul(sides_i),
+            TotalKind::Sum,
+        ),
+        Modifier::Targets(t, f, d) => {
+            let score = |v: u64| -> i64 {
+                if d > 0 && v >= d {
+                    2
+                } else if t > 0 && v >= t {
+                    1
+                } else if f > 0 && v <= f {
+                    -1
+                } else {
+                    0
+                }
+            };
+            // the score only changes around the target and failure values
+            let candidates = [
+                1,
+                sides,
+                t,
+                t.saturating_sub(1),
+                f,
+                f + 1,
+                d,
+                d.saturating_sub(1),
+            ];
+            let scores = candidates
+                .iter()
+                .filter(|&&v| 1 <= v && v <= sides)
+                .map(|&v| score(v));
+            let (low, high) = scores.fold((i64::MAX, i64::MIN), |(low, high), s| {
+                (low.min(s), high.max(s))
+            });
+            let ends = [
+                low * flat.0 as i64,
+                low * flat.1 as i64,
+                high * flat.0 as i64,
+                high * flat.1 as i64,
+            ];
+            (
+                *ends.iter().min().unwrap(),
+                *ends.iter().max().unwrap(),
+                TotalKind::Successes,
+            )
+        }
+        Modifier::TargetEnum(list) => {
+            let mut list: Vec<_> = list.into_iter().filter(|&v| 1 <= v && v <= sides).collect();
+            list.sort_unstable();
+            list.dedup();
+            let low = if (list.len() as u64) < sides { 0 } else { 1 };
+            let high = if list.is_empty() { 0 } else { 1 };
+            (
+                low * flat.0 as i64,
+                high * flat.1 as i64,
+                TotalKind::Successes,
+            )
+        }
+    };
+    Ok(Bounds {
+        min,
+        max,
+        mean: dice_mean(&dice, ctx)?,
+        kind: Some(kind),
+        max_dice,
+    })
+}
+
+fn expr_bounds(expr: Pairs<Rule>, limits: &RollerLimits, ctx: &AnalysisContext) -> Result<Bounds> {
+    Ok(operand(expr, limits, ctx)?.into_bounds(ctx.arithmetic.rounding))
+}
+
+fn operand(expr: Pairs<Rule>, limits: &RollerLimits, ctx: &AnalysisContext) -> Result<Operand> {
+    let res = parser::get_climber().climb(
+        expr,
+        |pair: Pair<Rule>| {
+            let span = Span::from(pair.as_span());
+            let res = match pair.as_rule() {
+                Rule::integer => pair
+                    .as_str()
+                    .replace(' ', "")
+                    .parse::<i64>()
+                    .map(|i| Operand::Int(Bounds::constant(i)))
+                    .map_err(|_| RollError::Overflow { span }),
+                Rule::float => parser::parse_decimal(&pair).map(|v| {
+                    Operand::Exact(ExactBounds {
+                        min: v,
+                        max: v,
+                        kind: None,
+                        max_dice: 0,
+                    })
+                }),
+                Rule::block_expr => {
+                    operand(pair.into_inner().next().unwrap().into_inner(), limits, ctx)
+                }
+                Rule::dice => dice_bounds(pair, limits, &ctx.at(span)).map(Operand::Int),
+                _ => unreachable!("{:#?}", pair),
+            };
+            res.map(|res| (res, span))
+        },
+        |lhs: Result<(Operand, Span)>, op: Pair<Rule>, rhs: Result<(Operand, Span)>| {
+            let (lhs, lspan) = lhs?;
+            let (rhs, rspan) = rhs?;
+            let span = lspan.join(rspan);
+            let res = match (op.as_rule(), lhs, rhs) {
+                (Rule::add, Operand::Int(l), Operand::Int(r)) => Operand::Int(Bounds {
+                    min: l.min.saturating_add(r.min),
+                    max: l.max.saturating_add(r.max),
+                    mean: l.mean.and_then(|lm| r.mean.map(|rm| lm + rm)),
+                    ..Bounds::from_candidates(std::iter::empty(), &l, &r)
+                }),
+                (Rule::sub, Operand::Int(l), Operand::Int(r)) => Operand::Int(Bounds {
+                    min: l.min.saturating_sub(r.max),
+                    max: l.max.saturating_sub(r.min),
+                    mean: l.mean.and_then(|lm| r.mean.map(|rm| lm - rm)),
+                    ..Bounds::from_candidates(std::iter::empty(), &l, &r)
+                }),
+                (Rule::mul, Operand::Int(l), Operand::Int(r)) => {
+                    let candidates = [
+                        l.min.saturating_mul(r.min),
+                        l.min.saturating_mul(r.max),
+                        l.max.saturating_mul(r.min),
+                        l.max.saturating_mul(r.max),
+                    ];
+                    Operand::Int(Bounds {
+                        // the operands are independent
+                        mean: l.mean.and_then(|lm| r.mean.map(|rm| lm * rm)),
+                        ..Bounds::from_candidates(candidates.iter().copied(), &l, &r)
+                    })
+                }
+                (Rule::div, l, r) => {
+                    // like the distribution, reject a divisor which can be zero
+                    if r.is_maybe_zero() {
+                        return Err(RollError::DivideByZero { span: rspan });
+                    }
+                    match (l, r) {
+                        // the integer division truncates, and the divisor doesn't change sign
+                        (Operand::Int(l), Operand::Int(r)) if !ctx.arithmetic.exact_division => {
+                            let candidates = [
+                                l.min.checked_div(r.min),
+                                l.min.checked_div(r.max),
+                                l.max.checked_div(r.min),
+                                l.max.checked_div(r.max),
+                            ];
+                            Operand::Int(Bounds::from_candidates(
+                                candidates.iter().map(|c| c.unwrap_or(i64::MAX)),
+                                &l,
+                                &r,
+                            ))
+                        }
+                        (l, r) => exact_bounds(&l, &r, Rational::checked_div, |l, r| l / r, span)?,
+                    }
+                }
+                (rule, l, r) => {
+                    let (exact_op, float_op): (fn(_, _) -> _, fn(_, _) -> _) = match rule {
+                        Rule::add => (Rational::checked_add, |l: f64, r: f64| l + r),
+                        Rule::sub => (Rational::checked_sub, |l: f64, r: f64| l - r),
+                        _ => (Rational::checked_mul, |l: f64, r: f64| l * r),
+                    };
+                    exact_bounds(&l, &r, exact_op, float_op, span)?
+                }
+            };
+            Ok((res, span))
+        },
+        // labels don't change the total
+        |lhs: Result<(Operand, Span)>, _label: Pair<Rule>| lhs,
+    );
+    res.map(|(res, _)| res)
+}
+
+// The number of repetitions, checked against the limits
+pub(crate) fn repetitions(number: &Pair<Rule>, limits: &RollerLimits) -> Result<u64> {
+    let span = number.as_span().into();
+    let number: u64 = parser::parse_number(number)?;
+    if number == 0 {
+        Err(RollError::InvalidRepetition { span })
+    } else if number > limits.max_repetitions {
+        Err(RollError::TooManyRepetitions {
+            span,
+            requested: number,
+            max: limits.max_repetitions,
+        })
+    } else {
+        Ok(number)
+    }
+}
+
+impl Roller {
+    /// Get the bounds, the average and the cost of the expression without rolling it, with the
+    /// limits of this roller.
+    ///
+    /// The errors which don't depend on the dice, like too many dice or sides, are reported. The
+    /// expression is never evaluated: the average is `None` when it can't be computed analytically,
+    /// like for a division by dice or a rounded fraction, see [`Roller::distribution()`] then.
+    pub fn analyze(&self) -> Result<Analysis> {
+        let limits = *self.get_limits();
+        let options = AnalysisOptions {
+            max_outcomes: MAX_OUTCOMES,
+            ..AnalysisOptions::with_limits(&limits)
+        };
+        let mut pairs = self.parse()?;
+        let expr_type = pairs.next().unwrap();
+        let ctx = AnalysisContext {
+            options: &options,
+            limits,
+            arithmetic: self.get_arithmetic(),
+            span: expr_type.as_span().into(),
+        };
+        let bounds = match expr_type.as_rule() {
+            Rule::expr => expr_bounds(expr_type.into_inner(), &limits, &ctx)?,
+            Rule::repeated_expr => {
+                let mut pairs = expr_type.into_inner();
+                let expr = pairs.next().unwrap();
+                let maybe_option = pairs.next().unwrap();
+                let (number, sum_all) = match maybe_option.as_rule() {
+                    Rule::number => (maybe_option, false),
+                    _ => (pairs.next().unwrap(), maybe_option.as_rule() == Rule::add),
+                };
+                let number = repetitions(&number, &limits)?;
+                let bounds = expr_bounds(expr.into_inner(), &limits, &ctx)?;
+                let max_dice = bounds.max_dice.saturating_mul(number);
+                if sum_all {
+                    let n = number as i64;
+                    Bounds {
+                        min: bounds.min.saturating_mul(n),
+                        max: bounds.max.saturating_mul(n),
+                        mean: bounds.mean.map(|mean| mean * number as f64),
+                        max_dice,
+                        ..bounds
+                    }
+                } else {
+                    Bounds { max_dice, ..bounds }
+                }
+            }
+            _ => unreachable!(),
+        };
+        Ok(Analysis {
+            min: bounds.min,
+            max: bounds.max,
+            average: bounds.mean,
+            kind: bounds.kind.unwrap_or(TotalKind::Sum),
+            max_dice: bounds.max_dice,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Arithmetic;
+
+    fn analyze(input: &str) -> (i64, i64, Option<f64>, TotalKind, u64) {
+        let a = Roller::new(input).unwrap().analyze().unwrap();
+        (
+            a.get_min(),
+            a.get_max(),
+            a.get_average().map(|avg| (avg * 1000.0).round() / 1000.0),
+            a.get_kind(),
+            a.get_max_dice(),
+        )
+    }
+
+    #[test]
+    fn analyze_test() {
+        use TotalKind::*;
+        assert_eq!((5, 20, Some(12.5), Sum, 3), analyze("3d6 + 2"));
+        assert_eq!((3, 18, Some(12.245), Sum, 4), analyze("4d6 K3"));
+        assert_eq!((-4, 4, Some(0.0), Sum, 4), analyze("4dF"));
+        assert_eq!((-5, 5, Some(0.0), Sum, 2), analyze("1d6 - 1d6"));
+        assert_eq!((1, 36, Some(12.25), Sum, 2), analyze("1d6 * 1d6"));
+        assert_eq!((0, 20, None, Sum, 2), analyze("1d20 / 1d4"));
+        // the rounding of the total is not linear, the average is not computed
+        assert_eq!((4, 27, None, Sum, 3), analyze("3d6 * 1.5"));
+        assert_eq!((4, 19, None, Sum, 1), analyze("(1d6 * 1.5 + 0.5) * 2"));
+        assert_eq!(
+            (-5, 10, Some(1.5), Successes, 5),
+            analyze("5d10 t8 tt10 f1")
+        );
+        assert_eq!((0, 3, Some(1.5), Successes, 3), analyze("3d6 t[2,4,6]"));
+        assert_eq!((1, 6, Some(3.5), Mixed, 4), analyze("3d6 t4 + 1d3"));
+        assert_eq!((3, 36, Some(12.25), Sum, 6), analyze("3d6 e6"));
+        assert_eq!((3, 18, Some(13.5), Sum, 3 + 3 * 100), analyze("3d6 ir2"));
+        assert_eq!((6, 21, Some(13.5), Sum, 3), analyze("(1d6 + 1)^+3"));
+        assert_eq!((2, 12, Some(7.0), Sum, 20), analyze("(2d6)^10"));
+        assert_eq!((5, 5, Some(5.0), Sum, 0), analyze("2 + 3"));
+        // more dice rolled than the roller allows, but the bounds are known
+        assert_eq!((4, 24), {
+            let a = Roller::new("4d6 e6 K4").unwrap().analyze().unwrap();
+            (a.get_min(), a.get_max())
+        });
+        let a = Roller::new("500d5000 ie5000 K200")
+            .unwrap()
+            .analyze()
+            .unwrap();
+        assert_eq!(
+            (200, 1_000_000, None),
+            (a.get_min(), a.get_max(), a.get_average())
+        );
+        assert_eq!(500 + 500 * 100, a.get_max_dice());
+
+        let arithmetic = Arithmetic {
+            exact_division: true,
+            rounding: FloatRounding::Nearest,
+        };
+        let a = Roller::new("(1d6 / 4) * 4 + 1d6 / 4")
+            .unwrap()
+            .with_arithmetic(arithmetic)
+            .analyze()
+            .unwrap();
+        // 1.25 to 7.5
+        assert_eq!((1, 8), (a.get_min(), a.get_max()));
+        assert_eq!(None, a.get_average());
+
+        // options which can't be analysed, or too many outcomes: no average
+        let a = Roller::new("4d6 e6 r1").unwrap().analyze().unwrap();
+        assert_eq!(None, a.get_average());
+        let a = Roller::new("2d1000 ie1000").unwrap().analyze().unwrap();
+        assert_eq!(None, a.get_average());
+
+        assert_eq!(
+            Err(RollError::NotEnoughDice {
+                span: Span::new(0, 6),
+                requested: 4,
+                available: 3
+            }),
+            Roller::new("3d6 K4").unwrap().analyze()
+        );
+        assert_eq!(
+            Err(RollError::DivideByZero {
+                span: Span::new(6, 15)
+            }),
+            Roller::new("1d6 / (1d3 - 2)").unwrap().analyze()
+        );
+        assert!(matches!(
+            Roller::new("6000d6").unwrap().analyze(),
+            Err(RollError::TooManyDice { .. })
+        ));
+        assert_eq!(
+            "5",
+            Roller::new("5").unwrap().analyze().unwrap().to_string()
+        );
+        assert_eq!(
+            "3–18, avg 12.24",
+            Roller::new("4d6 K3")
+                .unwrap()
+                .analyze()
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    // The bounds and the average follow what the roller gives
+    #[test]
+    fn analyze_matches_roller_test() {
+        let inputs = [
+            "3d6 + 2",
+            "4d6 K3",
+            "4d6 k1 + 1d4",
+            "5d6 D2",
+            "5d6 d2",
+            "4dF - 1",
+            "2d6 - 1d8",
+            "1d6 * 1d4",
+            "1d20 / 1d4",
+            "(1d6 - 4) * (1d4 - 2)",
+            "3d6 e6",
+            "2d6 r2",
+            "3d6 ir2",
+            "5d10 t8 tt10 f1",
+            "4d6 t[2,4,6]",
+            "3d6 t4 + 1d3",
+            "3d6 * 1.5",
+            "(1d6 * 1.5 + 0.5) * 2",
+            "1d8 / 3 * 2",
+        ];
+        let limits = RollerLimits::default();
+        let options = AnalysisOptions {
+            explosion_depth: limits.max_explosions,
+            reroll_depth: limits.max_rerolls,
+            ..Default::default()
+        };
+        for input in inputs.iter() {
+            let roller = Roller::new(input).unwrap();
+            let analysis = roller.analyze().unwrap();
+            let dist = roller.distribution_with(&options).unwrap();
+            assert_eq!(
+                (dist.get_min(), dist.get_max()),
+                (analysis.get_min(), analysis.get_max()),
+                "{}",
+                input
+            );
+            if let Some(average) = analysis.get_average() {
+                assert!((dist.mean() - average).abs() < 1e-9, "{}", input);
+            }
+            let mut source = crate::SeededSource::new(3);
+            for _ in 0..200 {
+                let res = roller.roll_with_source(&mut source).unwrap();
+                let total = res.as_single().unwrap().get_total();
+                assert!(
+                    analysis.get_min() <= total && total <= analysis.get_max(),
+                    "{} gave {}",
+                    input,
+                    total
+                );
+            }
+        }
+    }
+}
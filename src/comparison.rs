@@ -0,0 +1,176 @@
+use crate::{
+    distribution::AnalysisContext,
+    error::{Result, RollError, Span},
+    simulation::{simulate_totals, single_total},
+    AnalysisOptions, Distribution, Roller, SimulationOptions,
+};
+
+/// The outcome of two expressions rolled against each other, see [`Roller::compare()`].
+///
+/// # Examples
+///
+/// ```
+/// use caith::Roller;
+///
+/// let a = Roller::new("2d6 + 3").unwrap();
+/// let b = Roller::new("1d12 + 4").unwrap();
+/// let cmp = a.compare(&b).unwrap();
+/// assert!(cmp.is_exact());
+/// // `1d12 + 4` wins more often
+/// assert!(cmp.loss() > cmp.win());
+/// assert!((cmp.win() + cmp.tie() + cmp.loss() - 1.0).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    difference: Distribution,
+    exact: bool,
+}
+
+impl Comparison {
+    /// Get the probability that the first expression has a greater total
+    pub fn win(&self) -> f64 {
+        self.difference.at_least(1)
+    }
+
+    /// Get the probability that both expressions have the same total
+    pub fn tie(&self) -> f64 {
+        self.difference.probability(0)
+    }
+
+    /// Get the probability that the second expression has a greater total
+    pub fn loss(&self) -> f64 {
+        self.difference.at_most(-1)
+    }
+
+    /// Get the distribution of the total of the first expression minus the one of the second
+    pub fn get_difference(&self) -> &Distribution {
+        &self.difference
+    }
+
+    /// Says if the probabilities are exact, or estimated by a simulation
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+}
+
+impl Roller {
+    /// Compare the totals of this expression and `other`, with the default [`AnalysisOptions`] and
+    /// [`SimulationOptions`].
+    pub fn compare(&self, other: &Roller) -> Result<Comparison> {
+        self.compare_with(
+            other,
+            &AnalysisOptions::default(),
+            &SimulationOptions::default(),
+        )
+    }
+
+    /// Compare the totals of this expression and `other`.
+    ///
+    /// The comparison is exact when the distribution of both expressions and of their difference
+    /// can be computed, see [`Roller::distribution_with()`]. Otherwise, both expressions are rolled
+    /// against each other according to `simulation`: dice estimated to have too many outcomes
+    /// go straight to the simulation, without computing any distribution.
+    pub fn compare_with(
+        &self,
+        other: &Roller,
+        analysis: &AnalysisOptions,
+        simulation: &SimulationOptions,
+    ) -> Result<Comparison> {
+        let ctx = AnalysisContext {
+            options: analysis,
+            limits: *self.get_limits(),
+            arithmetic: self.get_arithmetic(),
+            span: Span::new(0, self.as_str().len()),
+        };
+        let exact = self
+            .check_complexity(analysis)
+            .and_then(|_| other.check_complexity(analysis))
+            .and_then(|_| {
+                let a = self.distribution_with(analysis)?;
+                let b = other.distribution_with(analysis)?;
+                a.add(&b.neg(&ctx)?, &ctx)
+            });
+        match exact {
+            Ok(difference) => Ok(Comparison {
+                difference,
+                exact: true,
+            }),
+            Err(RollError::TooComplex { .. }) => {
+                let pairs_a = self.parse()?;
+                let span_a = pairs_a.clone().next().unwrap().as_span().into();
+                let pairs_b = other.parse()?;
+                let span_b = pairs_b.clone().next().unwrap().as_span().into();
+                let sim = simulate_totals(simulation, |source| {
+                    let a = single_total(&self.roll_parsed(pairs_a.clone(), source)?, span_a)?;
+                    let b = single_total(&other.roll_parsed(pairs_b.clone(), source)?, span_b)?;
+                    a.checked_sub(b).ok_or(RollError::Overflow { span: span_a })
+                })?;
+                Ok(Comparison {
+                    difference: Distribution::from_simulation(&sim, &ctx)?,
+                    exact: false,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparison_test() {
+        let cmp = Roller::new("1d6")
+            .unwrap()
+            .compare(&Roller::new("1d6").unwrap())
+            .unwrap();
+        assert!(cmp.is_exact());
+        assert!((cmp.tie() - 1.0 / 6.0).abs() < 1e-9);
+        assert!((cmp.win() - 15.0 / 36.0).abs() < 1e-9);
+        assert!((cmp.loss() - 15.0 / 36.0).abs() < 1e-9);
+        assert_eq!(
+            (-5, 5),
+            (
+                cmp.get_difference().get_min(),
+                cmp.get_difference().get_max()
+            )
+        );
+
+        // too many outcomes for an exact computation
+        let options = AnalysisOptions {
+            max_outcomes: 1000,
+            ..Default::default()
+        };
+        let simulation = SimulationOptions {
+            iterations: 20_000,
+            ..Default::default()
+        };
+        let a = Roller::new("20d20").unwrap();
+        let b = Roller::new("20d20 + 5").unwrap();
+        let cmp = a.compare_with(&b, &options, &simulation).unwrap();
+        assert!(!cmp.is_exact());
+        assert!((cmp.get_difference().mean() + 5.0).abs() < 0.5);
+        assert!(cmp.loss() > cmp.win());
+        assert!((cmp.win() + cmp.tie() + cmp.loss() - 1.0).abs() < 1e-9);
+
+        // too many sequences of dice to enumerate, estimated without enumerating them
+        let c = Roller::new("7d10 e10 r1").unwrap();
+        let cmp = c
+            .compare_with(&Roller::new("7d10").unwrap(), &options, &simulation)
+            .unwrap();
+        assert!(!cmp.is_exact());
+        assert!(cmp.win() > cmp.loss());
+        assert!(matches!(
+            c.check_complexity(&AnalysisOptions::default()),
+            Err(RollError::TooComplex {
+                span: Span { start: 0, end: 11 }
+            })
+        ));
+
+        assert!(matches!(
+            a.compare(&Roller::new("(1d6)^2").unwrap()),
+            Err(RollError::NoSingleTotal { .. })
+        ));
+    }
+}
@@ -0,0 +1,1314 @@
+use std::collections::HashMap;
+
+use pest::{
+    iterators::{Pair, Pairs},
+    Parser,
+};
+
+use crate::{
+    analysis,
+    error::{Result, RollError, Span},
+    parser::{self, RollContext, RollParser, Rule},
+    Arithmetic, DiceRollSource, Rational, Roller, RollerLimits, Simulation,
+};
+
+/// Options of the exact analysis of an expression, see [`Roller::distribution_with()`].
+///
+/// # Examples
+///
+/// ```
+/// use caith::{AnalysisOptions, Roller};
+///
+/// let options = AnalysisOptions {
+///     explosion_depth: 3,
+///     ..Default::default()
+/// };
+/// let dist = Roller::new("1d6 ie6").unwrap().distribution_with(&options).unwrap();
+/// // the fourth die doesn't explode
+/// assert_eq!(24, dist.get_max());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnalysisOptions {
+    /// Maximum number of successive explosions of a die. The distribution is the one of a roller
+    /// with [`RollerLimits::max_explosions`] set to this value.
+    pub explosion_depth: u64,
+    /// Maximum number of rerolls of a die. The distribution is the one of a roller with
+    /// [`RollerLimits::max_rerolls`] set to this value.
+    pub reroll_depth: u64,
+    /// Maximum number of outcomes considered in one step of the analysis. Going over it gives
+    /// [`RollError::TooComplex`].
+    pub max_outcomes: u64,
+    /// Maximum number of dice rolled to enumerate the outcomes of dice whose options can't be
+    /// analysed, like `4d6 e6 r1`. Going over it, or being estimated to, gives
+    /// [`RollError::TooComplex`].
+    pub max_rolls: u64,
+}
+
+impl AnalysisOptions {
+    /// The default options, with the explosion and reroll depths of `limits`
+    pub fn with_limits(limits: &RollerLimits) -> Self {
+        AnalysisOptions {
+            explosion_depth: limits.max_explosions,
+            reroll_depth: limits.max_rerolls,
+            ..Default::default()
+        }
+    }
+}
+
+/// The depths are the ones of [`RollerLimits::default()`], so that the distribution has the
+/// totals of a default [`Roller`].
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        let limits = RollerLimits::default();
+        AnalysisOptions {
+            explosion_depth: limits.max_explosions,
+            reroll_depth: limits.max_rerolls,
+            max_outcomes: 10_000_000,
+            max_rolls: 1_000_000,
+        }
+    }
+}
+
+/// The probability of each possible total of an expression.
+///
+/// # Examples
+///
+/// ```
+/// use caith::Roller;
+///
+/// let dist = Roller::new("4d6 d1").unwrap().distribution().unwrap();
+/// assert_eq!(3, dist.get_min());
+/// assert_eq!(18, dist.get_max());
+/// assert!((dist.mean() - 12.24).abs() < 0.01);
+/// assert!((dist.at_least(15) - 0.2315).abs() < 0.0001);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    // probability of `min + index`, the first and last ones are not 0
+    min: i64,
+    probabilities: Vec<f64>,
+}
+
+impl Distribution {
+    pub(crate) fn constant(total: i64) -> Self {
+        Distribution {
+            min: total,
+            probabilities: vec![1.0],
+        }
+    }
+
+    // a die of `sides` sides
+    fn uniform(sides: u64) -> Self {
+        Distribution {
+            min: 1,
+            probabilities: vec![1.0 / sides as f64; sides as usize],
+        }
+    }
+
+    pub(crate) fn from_map(map: HashMap<i64, f64>, ctx: &AnalysisContext) -> Result<Self> {
+        let (min, max) = map
+            .iter()
+            .filter(|(_, &p)| p > 0.0)
+            .fold((i64::MAX, i64::MIN), |(min, max), (&v, _)| {
+                (min.min(v), max.max(v))
+            });
+        if min > max {
+            return Ok(Distribution::constant(0));
+        }
+        let len = (max as i128 - min as i128 + 1) as u128;
+        if len > ctx.options.max_outcomes as u128 {
+            return Err(ctx.too_complex());
+        }
+        let mut probabilities = vec![0.0; len as usize];
+        for (v, p) in map.into_iter() {
+            if p > 0.0 {
+                probabilities[(v - min) as usize] += p;
+            }
+        }
+        Ok(Distribution { min, probabilities })
+    }
+
+    // the frequencies observed by a simulation
+    pub(crate) fn from_simulation(sim: &Simulation, ctx: &AnalysisContext) -> Result<Self> {
+        let iterations = sim.get_iterations() as f64;
+        let map = sim
+            .get_histogram()
+            .iter()
+            .map(|(&total, &count)| (total, count as f64 / iterations))
+            .collect();
+        Distribution::from_map(map, ctx)
+    }
+
+    fn trimmed(mut self) -> Self {
+        let first = self.probabilities.iter().position(|&p| p > 0.0);
+        match first {
+            Some(first) => {
+                let last = self.probabilities.iter().rposition(|&p| p > 0.0).unwrap();
+                self.probabilities.truncate(last + 1);
+                self.probabilities.drain(..first);
+                self.min += first as i64;
+                self
+            }
+            None => Distribution::constant(0),
+        }
+    }
+
+    /// Get the lowest possible total
+    pub fn get_min(&self) -> i64 {
+        self.min
+    }
+
+    /// Get the highest possible total
+    pub fn get_max(&self) -> i64 {
+        self.min + self.probabilities.len() as i64 - 1
+    }
+
+    /// Get the probability of each possible total, in increasing order of total. Totals with a
+    /// probability of 0 are skipped.
+    pub fn pmf(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.probabilities
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p > 0.0)
+            .map(move |(idx, &p)| (self.min + idx as i64, p))
+    }
+
+    /// Get the probability of each total to be lower or equal to this total, in increasing order
+    /// of total. Totals with a probability of 0 are skipped.
+    pub fn cdf(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.pmf().scan(0.0, |acc, (total, p)| {
+            *acc += p;
+            Some((total, acc.min(1.0)))
+        })
+    }
+
+    /// Get the probability to get exactly `total`
+    pub fn probability(&self, total: i64) -> f64 {
+        if total < self.min {
+            0.0
+        } else {
+            self.probabilities
+                .get((total as i128 - self.min as i128) as usize)
+                .copied()
+                .unwrap_or(0.0)
+        }
+    }
+
+    /// Get the probability to get `total` or less
+    pub fn at_most(&self, total: i64) -> f64 {
+        if total < self.min {
+            0.0
+        } else if total >= self.get_max() {
+            1.0
+        } else {
+            let end = (total - self.min) as usize;
+            self.probabilities[..=end].iter().sum::<f64>().min(1.0)
+        }
+    }
+
+    /// Get the probability to get `total` or more
+    pub fn at_least(&self, total: i64) -> f64 {
+        if total <= self.min {
+            1.0
+        } else if total > self.get_max() {
+            0.0
+        } else {
+            let start = (total - self.min) as usize;
+            self.probabilities[start..].iter().sum::<f64>().min(1.0)
+        }
+    }
+
+    // probability to get each total or more, from the highest total to the lowest one, totals with
+    // a probability of 0 included
+    pub(crate) fn survival(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        let max = self.get_max();
+        self.probabilities
+            .iter()
+            .rev()
+            .enumerate()
+            .scan(0.0, move |acc, (idx, &p)| {
+                *acc += p;
+                Some((max - idx as i64, f64::min(*acc, 1.0)))
+            })
+    }
+
+    /// Get the expected total
+    pub fn mean(&self) -> f64 {
+        self.pmf().map(|(total, p)| total as f64 * p).sum()
+    }
+
+    /// Get the variance of the total
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean();
+        self.pmf()
+            .map(|(total, p)| (total as f64 - mean).powi(2) * p)
+            .sum()
+    }
+
+    /// Get the standard deviation of the total
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    // distribution of `self + other`
+    pub(crate) fn add(&self, other: &Self, ctx: &AnalysisContext) -> Result<Self> {
+        let min = self.min.checked_add(other.min).ok_or(ctx.overflow())?;
+        self.get_max()
+            .checked_add(other.get_max())
+            .ok_or(ctx.overflow())?;
+        let work = self.probabilities.len() as u128 * other.probabilities.len() as u128;
+        if work > ctx.options.max_outcomes as u128 {
+            return Err(ctx.too_complex());
+        }
+        let mut probabilities = vec![0.0; self.probabilities.len() + other.probabilities.len() - 1];
+        for (i, &p) in self.probabilities.iter().enumerate() {
+            if p > 0.0 {
+                for (j, &q) in other.probabilities.iter().enumerate() {
+                    probabilities[i + j] += p * q;
+                }
+            }
+        }
+        Ok(Distribution { min, probabilities }.trimmed())
+    }
+
+    pub(crate) fn neg(&self, ctx: &AnalysisContext) -> Result<Self> {
+        let min = self.get_max().checked_neg().ok_or(ctx.overflow())?;
+        self.min.checked_neg().ok_or(ctx.overflow())?;
+        let mut probabilities = self.probabilities.clone();
+        probabilities.reverse();
+        Ok(Distribution { min, probabilities })
+    }
+
+    // distribution of the sum of `count` independent totals following `self`
+    pub(crate) fn repeat(&self, count: u64, ctx: &AnalysisContext) -> Result<Self> {
+        let mut res = Distribution::constant(0);
+        let mut power = self.clone();
+        let mut count = count;
+        while count > 0 {
+            if count & 1 == 1 {
+                res = res.add(&power, ctx)?;
+            }
+            count >>= 1;
+            if count > 0 {
+                power = power.add(&power, ctx)?;
+            }
+        }
+        Ok(res)
+    }
+
+    // distribution of `op(self, other)` for any operation, `None` being an overflow
+    fn combine<F: Fn(i64, i64) -> Option<i64>>(
+        &self,
+        other: &Self,
+        op: F,
+        ctx: &AnalysisContext,
+    ) -> Result<Self> {
+        let work = self.probabilities.len() as u128 * other.probabilities.len() as u128;
+        if work > ctx.options.max_outcomes as u128 {
+            return Err(ctx.too_complex());
+        }
+        let mut map = HashMap::new();
+        for (l, p) in self.pmf() {
+            for (r, q) in other.pmf() {
+                let total = op(l, r).ok_or(ctx.overflow())?;
+                *map.entry(total).or_insert(0.0) += p * q;
+            }
+        }
+        Distribution::from_map(map, ctx)
+    }
+}
+
+// State of an analysis: the options, and the expression being analysed to report errors
+pub(crate) struct AnalysisContext<'a> {
+    pub(crate) options: &'a AnalysisOptions,
+    pub(crate) limits: RollerLimits,
+    pub(crate) arithmetic: Arithmetic,
+    pub(crate) span: Span,
+}
+
+impl AnalysisContext<'_> {
+    // the same analysis, reporting errors on another part of the expression
+    pub(crate) fn at(&self, span: Span) -> Self {
+        AnalysisContext {
+            options: self.options,
+            limits: self.limits,
+            arithmetic: self.arithmetic,
+            span,
+        }
+    }
+
+    pub(crate) fn too_complex(&self) -> RollError {
+        RollError::TooComplex { span: self.span }
+    }
+
+    fn overflow(&self) -> RollError {
+        RollError::Overflow { span: self.span }
+    }
+}
+
+// A part of the expression involving a fraction keeps its exact values, like the roller, the total
+// being rounded at the end
+enum Operand {
+    Dist(Distribution),
+    // each value with its probability
+    Exact(Vec<(Rational, f64)>),
+}
+
+impl Operand {
+    fn into_distribution(self, ctx: &AnalysisContext) -> Result<Distribution> {
+        match self {
+            Operand::Dist(dist) => Ok(dist),
+            Operand::Exact(values) => {
+                let mut map = HashMap::new();
+                for (v, p) in values.into_iter() {
+                    *map.entry(v.round(ctx.arithmetic.rounding)).or_insert(0.0) += p;
+                }
+                Distribution::from_map(map, ctx)
+            }
+        }
+    }
+
+    fn is_maybe_zero(&self) -> bool {
+        match self {
+            Operand::Dist(dist) => dist.probability(0) > 0.0,
+            Operand::Exact(values) => values.iter().any(|&(v, p)| v.numer() == 0 && p > 0.0),
+        }
+    }
+
+    fn values(&self) -> Vec<(Rational, f64)> {
+        match self {
+            Operand::Dist(dist) => dist.pmf().map(|(v, p)| (v.into(), p)).collect(),
+            Operand::Exact(values) => values.clone(),
+        }
+    }
+}
+
+fn apply(
+    lhs: Operand,
+    rhs: Operand,
+    int_op: fn(i64, i64) -> Option<i64>,
+    exact_op: fn(Rational, Rational) -> Option<Rational>,
+    ctx: &AnalysisContext,
+) -> Result<Operand> {
+    match (lhs, rhs) {
+        (Operand::Dist(l), Operand::Dist(r)) => l.combine(&r, int_op, ctx).map(Operand::Dist),
+        (lhs, rhs) => apply_exact(lhs, rhs, exact_op, ctx),
+    }
+}
+
+fn apply_exact(
+    lhs: Operand,
+    rhs: Operand,
+    exact_op: fn(Rational, Rational) -> Option<Rational>,
+    ctx: &AnalysisContext,
+) -> Result<Operand> {
+    let (lhs, rhs) = (lhs.values(), rhs.values());
+    if lhs.len() as u128 * rhs.len() as u128 > ctx.options.max_outcomes as u128 {
+        return Err(ctx.too_complex());
+    }
+    let mut map = HashMap::new();
+    for &(l, p) in lhs.iter() {
+        for &(r, q) in rhs.iter() {
+            // the roller fails as soon as a total doesn't fit
+            let v = exact_op(l, r).ok_or(ctx.overflow())?;
+            *map.entry(v).or_insert(0.0) += p * q;
+        }
+    }
+    Ok(Operand::Exact(map.into_iter().collect()))
+}
+
+// Distribution of the total of a whole expression, following `parser::compute`
+pub(crate) fn expr_distribution(expr: Pairs<Rule>, ctx: &AnalysisContext) -> Result<Distribution> {
+    operand(expr, ctx)?.0.into_distribution(ctx)
+}
+
+// The operand, and whether it has labelled subtotals
+fn operand(expr: Pairs<Rule>, ctx: &AnalysisContext) -> Result<(Operand, bool)> {
+    let res = parser::get_climber().climb(
+        expr,
+        |pair: Pair<Rule>| {
+            let span = Span::from(pair.as_span());
+            let res = match pair.as_rule() {
+                Rule::integer => pair
+                    .as_str()
+                    .replace(' ', "")
+                    .parse::<i64>()
+                    .map(|i| (Operand::Dist(Distribution::constant(i)), false))
+                    .map_err(|_| RollError::Overflow { span }),
+                Rule::float => {
+                    parser::parse_decimal(&pair).map(|v| (Operand::Exact(vec![(v, 1.0)]), false))
+                }
+                Rule::block_expr => operand(pair.into_inner().next().unwrap().into_inner(), ctx),
+                Rule::dice => {
+                    let ctx = ctx.at(span);
+                    analysis::check_dice(pair.clone(), &ctx.limits, &ctx)?;
+                    dice_distribution(pair, &ctx).map(|dist| (Operand::Dist(dist), false))
+                }
+                _ => unreachable!("{:#?}", pair),
+            };
+            res.map(|(res, labelled)| (res, span, labelled))
+        },
+        |lhs: Result<(Operand, Span, bool)>, op: Pair<Rule>, rhs: Result<(Operand, Span, bool)>| {
+            let (lhs, lspan, llabelled) = lhs?;
+            let (rhs, rspan, rlabelled) = rhs?;
+            let span = lspan.join(rspan);
+            let ctx = ctx.at(span);
+            // like the roller, labelled subtotals are only scaled by a plain value
+            match op.as_rule() {
+                Rule::mul if llabelled && rlabelled => {
+                    return Err(RollError::InvalidLabelOperation { span });
+                }
+                Rule::div if rlabelled => {
+                    return Err(RollError::InvalidLabelOperation { span: rspan });
+                }
+                _ => (),
+            }
+            let res = match op.as_rule() {
+                Rule::add => match (lhs, rhs) {
+                    (Operand::Dist(l), Operand::Dist(r)) => l.add(&r, &ctx).map(Operand::Dist),
+                    (lhs, rhs) => apply(lhs, rhs, i64::checked_add, Rational::checked_add, &ctx),
+                },
+                Rule::sub => match (lhs, rhs) {
+                    (Operand::Dist(l), Operand::Dist(r)) => {
+                        l.add(&r.neg(&ctx)?, &ctx).map(Operand::Dist)
+                    }
+                    (lhs, rhs) => apply(lhs, rhs, i64::checked_sub, Rational::checked_sub, &ctx),
+                },
+                Rule::mul => apply(lhs, rhs, i64::checked_mul, Rational::checked_mul, &ctx),
+                Rule::div => {
+                    // the roller fails as soon as the divisor can be zero
+                    if rhs.is_maybe_zero() {
+                        return Err(RollError::DivideByZero { span: rspan });
+                    }
+                    if ctx.arithmetic.exact_division {
+                        apply_exact(lhs, rhs, Rational::checked_div, &ctx)
+                    } else {
+                        apply(lhs, rhs, i64::checked_div, Rational::checked_div, &ctx)
+                    }
+                }
+                _ => unreachable!(),
+            };
+            res.map(|res| (res, span, llabelled || rlabelled))
+        },
+        // labels don't change the total
+        |lhs: Result<(Operand, Span, bool)>, label: Pair<Rule>| {
+            lhs.map(|(res, span, _)| (res, span.join(label.as_span().into()), true))
+        },
+    );
+    res.map(|(res, _, labelled)| (res, labelled))
+}
+
+// The options of a dice expression, when their total can be computed without enumerating the dice
+enum DiceOptions {
+    // reroll once or indefinitely if lower or equal
+    Reroll {
+        value: u64,
+        indefinite: bool,
+    },
+    // explode once or indefinitely if greater or equal
+    Explode {
+        value: u64,
+        indefinite: bool,
+    },
+    // keep the `keep` highest or lowest dice, after a possible reroll
+    Keep {
+        reroll: Option<(u64, bool)>,
+        keep: usize,
+        highest: bool,
+    },
+    // success counting, value of each die
+    Target(Vec<(u64, i64)>),
+}
+
+// The number of dice, the number of sides (`None` for fudge dice) and the options
+pub(crate) fn parse_dice<'i>(dice: &Pair<'i, Rule>) -> Result<(u64, Option<u64>, Pairs<'i, Rule>)> {
+    let mut inner = dice.clone().into_inner();
+    let number_of_dice = inner.next().unwrap();
+    let number_of_dice: u64 = match number_of_dice.as_rule() {
+        Rule::nb_dice => {
+            inner.next(); // skip `d` token
+            parser::parse_number(&number_of_dice)?
+        }
+        _ => 1,
+    };
+    let sides_pair = inner.next().unwrap();
+    let sides = match sides_pair.as_rule() {
+        Rule::fudge => None,
+        _ => Some(parser::parse_number(&sides_pair)?),
+    };
+    Ok((number_of_dice, sides, inner))
+}
+
+fn dice_distribution(dice: Pair<Rule>, ctx: &AnalysisContext) -> Result<Distribution> {
+    let (number_of_dice, sides, options) = parse_dice(&dice)?;
+    let sides = match sides {
+        Some(sides) => sides,
+        // options are ignored for fudge dice
+        None => return fudge_die().repeat(number_of_dice, ctx),
+    };
+    match dice_options(options.clone(), sides, number_of_dice)? {
+        Some(options) => analytic_distribution(number_of_dice, sides, options, ctx),
+        None => enumerated_distribution(dice, number_of_dice, sides, options, ctx),
+    }
+}
+
+// Every sequence of the dice is enumerated, once their number is estimated
+fn enumerated_distribution(
+    dice: Pair<Rule>,
+    nb: u64,
+    sides: u64,
+    options: Pairs<Rule>,
+    ctx: &AnalysisContext,
+) -> Result<Distribution> {
+    estimate_rolls(nb, sides, options, ctx)?;
+    enumerate_distribution(dice, ctx).map(|(dist, _)| dist)
+}
+
+// Explosions and rerolls multiply the sequences of dice, so their number is estimated from the
+// sequences of one die, without keep nor drop: the dice being rolled the same way, there are about
+// as many sequences as the ones of a die to the power of the number of dice, each rolling at least
+// as many dice.
+fn estimate_rolls(nb: u64, sides: u64, options: Pairs<Rule>, ctx: &AnalysisContext) -> Result<()> {
+    if nb > 1 {
+        let options: Vec<_> = options
+            .filter(|option| {
+                !matches!(
+                    option.as_rule(),
+                    Rule::keep_hi | Rule::keep_lo | Rule::drop_hi | Rule::drop_lo
+                )
+            })
+            .map(|option| option.as_str())
+            .collect();
+        let one_die = format!("1d{} {}", sides, options.join(" "));
+        // the options come from a valid dice expression
+        let one_die = RollParser::parse(Rule::dice, &one_die)
+            .unwrap()
+            .next()
+            .unwrap();
+        let (_, paths) = enumerate_distribution(one_die, ctx)?;
+        if (paths as f64).powf(nb as f64) * nb as f64 > ctx.options.max_rolls as f64 {
+            return Err(ctx.too_complex());
+        }
+    }
+    Ok(())
+}
+
+// Expected total of a dice expression when it can be computed analytically, `None` when its
+// options can't be analysed or it has too many outcomes
+pub(crate) fn dice_mean(dice: &Pair<Rule>, ctx: &AnalysisContext) -> Result<Option<f64>> {
+    let (number_of_dice, sides, options) = parse_dice(dice)?;
+    let die = match sides {
+        Some(sides) => match dice_options(options, sides, number_of_dice)? {
+            Some(options @ DiceOptions::Keep { .. }) => {
+                return match analytic_distribution(number_of_dice, sides, options, ctx) {
+                    Ok(dist) => Ok(Some(dist.mean())),
+                    Err(RollError::TooComplex { .. }) => Ok(None),
+                    Err(e) => Err(e),
+                };
+            }
+            Some(options) => match die_distribution(sides, &options, ctx) {
+                Err(RollError::TooComplex { .. }) => None,
+                res => res?,
+            },
+            None => None,
+        },
+        None => Some(fudge_die()),
+    };
+    Ok(die.map(|die| die.mean() * number_of_dice as f64))
+}
+
+fn fudge_die() -> Distribution {
+    Distribution {
+        min: -1,
+        probabilities: vec![1.0 / 3.0; 3],
+    }
+}
+
+// Recognize the options which can be analysed without enumerating, `None` otherwise
+fn dice_options(options: Pairs<Rule>, sides: u64, nb: u64) -> Result<Option<DiceOptions>> {
+    let options: Vec<_> = options.collect();
+    let value = |option: &Pair<Rule>| -> Result<Option<u64>> {
+        parser::extract_option_value(option.clone())
+    };
+    let keep = |option: &Pair<Rule>| -> Result<Option<(usize, bool)>> {
+        let n = value(option)?.unwrap_or(0) as usize;
+        let nb = nb as usize;
+        Ok(match option.as_rule() {
+            Rule::keep_hi => Some((n.min(nb), true)),
+            Rule::keep_lo => Some((n.min(nb), false)),
+            Rule::drop_hi => Some((nb.saturating_sub(n), false)),
+            Rule::drop_lo => Some((nb.saturating_sub(n), true)),
+            _ => None,
+        })
+    };
+    let reroll = |option: &Pair<Rule>| -> Result<Option<(u64, bool)>> {
+        Ok(match option.as_rule() {
+            Rule::reroll => Some((value(option)?.unwrap(), false)),
+            Rule::i_reroll => Some((value(option)?.unwrap(), true)),
+            _ => None,
+        })
+    };
+
+    let res = match options.as_slice() {
+        [] => Some(DiceOptions::Target(
+            (1..=sides).map(|v| (v, v as i64)).collect(),
+        )),
+        [option] => match option.as_rule() {
+            Rule::reroll | Rule::i_reroll => {
+                let (value, indefinite) = reroll(option)?.unwrap();
+                Some(DiceOptions::Reroll { value, indefinite })
+            }
+            Rule::explode | Rule::i_explode => Some(DiceOptions::Explode {
+                value: value(option)?.unwrap_or(sides),
+                indefinite: option.as_rule() == Rule::i_explode,
+            }),
+            Rule::keep_hi | Rule::keep_lo | Rule::drop_hi | Rule::drop_lo => {
+                let (keep, highest) = keep(option)?.unwrap();
+                Some(DiceOptions::Keep {
+                    reroll: None,
+                    keep,
+                    highest,
+                })
+            }
+            _ => target_options(&options, sides)?,
+        },
+        [first, second] if reroll(first)?.is_some() && keep(second)?.is_some() => {
+            let (keep, highest) = keep(second)?.unwrap();
+            Some(DiceOptions::Keep {
+                reroll: reroll(first)?,
+                keep,
+                highest,
+            })
+        }
+        _ => target_options(&options, sides)?,
+    };
+    Ok(res)
+}
+
+// Only targets and failures, merged like `parser::compute_roll` does
+fn target_options(options: &[Pair<Rule>], sides: u64) -> Result<Option<DiceOptions>> {
+    if let [option] = options {
+        if option.as_rule() == Rule::target {
+            let value_or_enum = option.clone().into_inner().next().unwrap();
+            if value_or_enum.as_rule() == Rule::target_enum {
+                let list = value_or_enum
+                    .into_inner()
+                    .map(|p| parser::parse_number::<u64>(&p))
+                    .collect::<Result<Vec<_>>>()?;
+                return Ok(Some(DiceOptions::Target(
+                    (1..=sides)
+                        .map(|v| (v, if list.contains(&v) { 1 } else { 0 }))
+                        .collect(),
+                )));
+            }
+        }
+    }
+
+    let (mut t, mut f, mut d) = (0, 0, 0);
+    for (idx, option) in options.iter().enumerate() {
+        let (nt, nf, nd) = match option.as_rule() {
+            Rule::target => {
+                let value = option.clone().into_inner().next().unwrap();
+                if value.as_rule() != Rule::number {
+                    return Ok(None);
+                }
+                (parser::parse_number(&value)?, 0, 0)
+            }
+            Rule::failure => (0, parser::extract_option_value(option.clone())?.unwrap(), 0),
+            Rule::double_target => (0, 0, parser::extract_option_value(option.clone())?.unwrap()),
+            _ => return Ok(None),
+        };
+        if idx == 0 {
+            t = nt;
+            f = nf;
+            d = nd;
+        } else if nt > 0 {
+            t = nt;
+        } else if nf > 0 {
+            f = nf;
+        } else {
+            d = nd;
+        }
+    }
+    let score = |v: u64| {
+        if d > 0 && v >= d {
+            2
+        } else if t > 0 && v >= t {
+            1
+        } else if f > 0 && v <= f {
+            -1
+        } else {
+            0
+        }
+    };
+    Ok(Some(DiceOptions::Target(
+        (1..=sides).map(|v| (v, score(v))).collect(),
+    )))
+}
+
+fn analytic_distribution(
+    nb: u64,
+    sides: u64,
+    options: DiceOptions,
+    ctx: &AnalysisContext,
+) -> Result<Distribution> {
+    match options {
+        DiceOptions::Keep {
+            reroll,
+            keep,
+            highest,
+        } => {
+            if sides as u128 > ctx.options.max_outcomes as u128 {
+                return Err(ctx.too_complex());
+            }
+            let die = Distribution::uniform(sides);
+            let die = match reroll {
+                Some((value, indefinite)) => reroll_distribution(&die, value, indefinite, ctx)?,
+                None => die,
+            };
+            keep_distribution(&die, nb, keep, highest, ctx)
+        }
+        _ => die_distribution(sides, &options, ctx)?
+            .unwrap()
+            .repeat(nb, ctx),
+    }
+}
+
+// Distribution of each die when the dice are independent, `None` for keep and drop
+fn die_distribution(
+    sides: u64,
+    options: &DiceOptions,
+    ctx: &AnalysisContext,
+) -> Result<Option<Distribution>> {
+    if sides as u128 > ctx.options.max_outcomes as u128 {
+        return Err(ctx.too_complex());
+    }
+    let die = Distribution::uniform(sides);
+    let res = match *options {
+        DiceOptions::Target(ref scores) => {
+            let mut map = HashMap::new();
+            for (_, score) in scores {
+                *map.entry(*score).or_insert(0.0) += 1.0 / sides as f64;
+            }
+            Distribution::from_map(map, ctx)?
+        }
+        DiceOptions::Reroll { value, indefinite } => {
+            reroll_distribution(&die, value, indefinite, ctx)?
+        }
+        DiceOptions::Explode { value, indefinite } => {
+            let depth = if indefinite {
+                ctx.options.explosion_depth
+            } else {
+                ctx.options.explosion_depth.min(1)
+            };
+            // a die exploded `n` times is a die plus, if it explodes, a die exploded `n - 1` times
+            let mut exploded = die.clone();
+            for _ in 0..depth {
+                if sides as u128 * exploded.probabilities.len() as u128
+                    > ctx.options.max_outcomes as u128
+                {
+                    return Err(ctx.too_complex());
+                }
+                let mut map = HashMap::new();
+                for v in 1..=sides {
+                    if v >= value {
+                        for (e, p) in exploded.pmf() {
+                            *map.entry(v as i64 + e).or_insert(0.0) += p / sides as f64;
+                        }
+                    } else {
+                        *map.entry(v as i64).or_insert(0.0) += 1.0 / sides as f64;
+                    }
+                }
+                exploded = Distribution::from_map(map, ctx)?;
+            }
+            exploded
+        }
+        DiceOptions::Keep { .. } => return Ok(None),
+    };
+    Ok(Some(res))
+}
+
+fn reroll_distribution(
+    die: &Distribution,
+    value: u64,
+    indefinite: bool,
+    ctx: &AnalysisContext,
+) -> Result<Distribution> {
+    let depth = if indefinite {
+        ctx.options.reroll_depth
+    } else {
+        ctx.options.reroll_depth.min(1)
+    };
+    // a die rerolled `n` times is kept if it's high enough, or is a die rerolled `n - 1` times
+    let mut rerolled = die.clone();
+    for _ in 0..depth {
+        let low = die.at_most(value as i64);
+        let mut probabilities = die.probabilities.clone();
+        for (idx, p) in probabilities.iter_mut().enumerate() {
+            if die.min + idx as i64 <= value as i64 {
+                *p = 0.0;
+            }
+            *p += low * rerolled.probability(die.min + idx as i64);
+        }
+        rerolled = Distribution {
+            min: die.min,
+            probabilities,
+        }
+        .trimmed();
+    }
+    Ok(rerolled)
+}
+
+// Sum of the `keep` highest or lowest of `nb` dice following `die`.
+//
+// The faces are considered from the best one: the number of dice showing a face follows a binomial
+// law among the dice not showing a better face. Once `keep` dice are assigned, the sum is known.
+fn keep_distribution(
+    die: &Distribution,
+    nb: u64,
+    keep: usize,
+    highest: bool,
+    ctx: &AnalysisContext,
+) -> Result<Distribution> {
+    let nb = nb as usize;
+    let mut faces: Vec<(i64, f64)> = die.pmf().collect();
+    if highest {
+        faces.reverse();
+    }
+    let max_sum = keep as u128 * die.get_max().unsigned_abs() as u128;
+    let work = faces.len() as u128 * (keep as u128 + 1) * (max_sum + 1) * (nb as u128 + 1);
+    if work > ctx.options.max_outcomes as u128 {
+        return Err(ctx.too_complex());
+    }
+
+    let ln_fact: Vec<f64> = std::iter::once(0.0)
+        .chain((1..=nb).scan(0.0, |acc, k| {
+            *acc += (k as f64).ln();
+            Some(*acc)
+        }))
+        .collect();
+    let binomial = |n: usize, k: usize, q: f64| -> f64 {
+        if q >= 1.0 {
+            if k == n {
+                1.0
+            } else {
+                0.0
+            }
+        } else if q <= 0.0 {
+            if k == 0 {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            (ln_fact[n] - ln_fact[k] - ln_fact[n - k]
+                + k as f64 * q.ln()
+                + (n - k) as f64 * (1.0 - q).ln())
+            .exp()
+        }
+    };
+
+    // probability of (dice assigned, sum of the kept ones) for less than `keep` dice assigned
+    let mut states: HashMap<(usize, i64), f64> = HashMap::new();
+    let mut res: HashMap<i64, f64> = HashMap::new();
+    if keep == 0 {
+        res.insert(0, 1.0);
+    } else {
+        states.insert((0, 0), 1.0);
+    }
+    let mut remaining_mass = 1.0;
+    for (face, p) in faces {
+        let q = if remaining_mass > 0.0 {
+            p / remaining_mass
+        } else {
+            1.0
+        };
+        remaining_mass -= p;
+        let mut next = HashMap::new();
+        for ((assigned, sum), w) in states.into_iter() {
+            let remaining = nb - assigned;
+            for c in 0..=remaining {
+                let b = binomial(remaining, c, q);
+                if b == 0.0 {
+                    continue;
+                }
+                let kept = c.min(keep - assigned);
+                let new_sum = sum + kept as i64 * face;
+                if assigned + c >= keep {
+                    *res.entry(new_sum).or_insert(0.0) += w * b;
+                } else {
+                    *next.entry((assigned + c, new_sum)).or_insert(0.0) += w * b;
+                }
+            }
+        }
+        states = next;
+    }
+    Distribution::from_map(res, ctx)
+}
+
+// Roll the dice expression with every possible sequence of dice
+struct PathSource {
+    // value and sides of each die rolled in the current sequence
+    path: Vec<(u64, u64)>,
+    pos: usize,
+}
+
+impl DiceRollSource for PathSource {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        if self.pos == self.path.len() {
+            self.path.push((1, sides));
+        }
+        let value = self.path[self.pos].0;
+        self.pos += 1;
+        value
+    }
+}
+
+// The distribution, and the number of sequences rolled
+fn enumerate_distribution(dice: Pair<Rule>, ctx: &AnalysisContext) -> Result<(Distribution, u64)> {
+    let mut source = PathSource {
+        path: Vec::new(),
+        pos: 0,
+    };
+    let mut map = HashMap::new();
+    let mut count = 0_u64;
+    let mut rolled = 0_u64;
+    loop {
+        count += 1;
+        source.pos = 0;
+        let total = {
+            let mut roll_ctx =
+                RollContext::new(&mut source, &ctx.limits).with_arithmetic(ctx.arithmetic);
+            parser::compute_roll(dice.clone(), &mut roll_ctx)?.get_total()
+        };
+        source.path.truncate(source.pos);
+        rolled += source.pos as u64;
+        if rolled > ctx.options.max_rolls {
+            return Err(ctx.too_complex());
+        }
+        let p: f64 = source
+            .path
+            .iter()
+            .map(|(_, sides)| 1.0 / *sides as f64)
+            .product();
+        *map.entry(total).or_insert(0.0) += p;
+
+        // next sequence
+        loop {
+            match source.path.last_mut() {
+                None => return Distribution::from_map(map, ctx).map(|dist| (dist, count)),
+                Some((value, sides)) if *value < *sides => {
+                    *value += 1;
+                    break;
+                }
+                Some(_) => {
+                    source.path.pop();
+                }
+            }
+        }
+    }
+}
+
+impl Roller {
+    /// Compute the exact distribution of the total, with the default [`AnalysisOptions`] and the
+    /// explosion and reroll depths of the limits of this roller, see
+    /// [`AnalysisOptions::with_limits()`].
+    pub fn distribution(&self) -> Result<Distribution> {
+        self.distribution_with(&AnalysisOptions::with_limits(self.get_limits()))
+    }
+
+    /// Compute the exact distribution of the total.
+    ///
+    /// Explosions and rerolls are considered up to the depth given in `options`. A repetition
+    /// must be summed with `^+` to have a total. The expression is never rolled: the distribution
+    /// is an error if the expression breaks the limits of the roller, or if it can fail to roll,
+    /// like a division by a value which can be zero.
+    ///
+    /// Dice with more outcomes than `options` allow give [`RollError::TooComplex`], as soon as
+    /// their number is estimated, before enumerating them.
+    pub fn distribution_with(&self, options: &AnalysisOptions) -> Result<Distribution> {
+        let mut pairs = self.parse()?;
+        let expr_type = pairs.next().unwrap();
+        let ctx = self.analysis_context(options, expr_type.as_span().into());
+        match expr_type.as_rule() {
+            Rule::expr => expr_distribution(expr_type.into_inner(), &ctx),
+            Rule::repeated_expr => {
+                let span = expr_type.as_span().into();
+                let mut pairs = expr_type.into_inner();
+                let expr = pairs.next().unwrap();
+                let maybe_option = pairs.next().unwrap();
+                if maybe_option.as_rule() != Rule::add {
+                    return Err(RollError::NoSingleTotal { span });
+                }
+                let number = analysis::repetitions(&pairs.next().unwrap(), &ctx.limits)?;
+                let dist = expr_distribution(expr.into_inner(), &ctx)?;
+                dist.repeat(number, &ctx.at(span))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Cheap check of the dice to enumerate, to give up before computing any distribution
+    pub(crate) fn check_complexity(&self, options: &AnalysisOptions) -> Result<()> {
+        let pairs = self.parse()?;
+        let ctx = self.analysis_context(options, Span::default());
+        for dice in pairs.flatten().filter(|pair| pair.as_rule() == Rule::dice) {
+            let (number_of_dice, sides, dice_opts) = parse_dice(&dice)?;
+            if let Some(sides) = sides {
+                if dice_options(dice_opts.clone(), sides, number_of_dice)?.is_none() {
+                    let ctx = ctx.at(dice.as_span().into());
+                    estimate_rolls(number_of_dice, sides, dice_opts, &ctx)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn analysis_context<'a>(
+        &self,
+        options: &'a AnalysisOptions,
+        span: Span,
+    ) -> AnalysisContext<'a> {
+        AnalysisContext {
+            options,
+            limits: RollerLimits {
+                max_explosions: options.explosion_depth,
+                max_rerolls: options.reroll_depth,
+                max_total_dice: u64::MAX,
+                ..*self.get_limits()
+            },
+            arithmetic: self.get_arithmetic(),
+            span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FloatRounding, MinSource};
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    fn assert_same(expected: &Distribution, actual: &Distribution) {
+        assert_eq!(
+            (expected.get_min(), expected.get_max()),
+            (actual.get_min(), actual.get_max())
+        );
+        for total in expected.get_min()..=expected.get_max() {
+            assert_close(expected.probability(total), actual.probability(total));
+        }
+    }
+
+    // compare the analytic computation of a dice expression with the enumeration of its dice
+    fn check_analytic(input: &str) {
+        let options = AnalysisOptions {
+            explosion_depth: 3,
+            reroll_depth: 3,
+            max_rolls: 10_000_000,
+            ..Default::default()
+        };
+        let analytic = Roller::new(input)
+            .unwrap()
+            .distribution_with(&options)
+            .unwrap();
+
+        let roller = Roller::new(input).unwrap();
+        let mut pairs = roller.parse().unwrap();
+        let dice = pairs.next().unwrap().into_inner().next().unwrap();
+        let ctx = AnalysisContext {
+            options: &options,
+            limits: RollerLimits {
+                max_explosions: 3,
+                max_rerolls: 3,
+                ..Default::default()
+            },
+            arithmetic: Arithmetic::default(),
+            span: Span::default(),
+        };
+        let (enumerated, _) = enumerate_distribution(dice, &ctx).unwrap();
+        assert_same(&enumerated, &analytic);
+        assert_close(1.0, analytic.pmf().map(|(_, p)| p).sum());
+    }
+
+    #[test]
+    fn analytic_matches_roller_test() {
+        for input in &[
+            "3d6",
+            "d8",
+            "4d6 K3",
+            "4d6 k2",
+            "5d4 D2",
+            "4d6 d1",
+            "3d6 K3",
+            "3d4 r1",
+            "3d4 ir2",
+            "3d4 e4",
+            "2d4 ie3",
+            "2d4 e2",
+            "4d4 r2 K2",
+            "3d4 ir1 d1",
+            "4d6 t5",
+            "4d6 t4 f1",
+            "4d6 tt6 t4",
+            "4d6 t4 tt6 f2",
+            "4d6 t[2,4,6]",
+            "3dF",
+            "4df K1",
+            "2d4 e4 r1",
+            "2d6 e5 r2 t4",
+            "3d4 e4 r1 K2",
+        ] {
+            check_analytic(input);
+        }
+    }
+
+    #[test]
+    fn distribution_test() {
+        let dist = Roller::new("2d6").unwrap().distribution().unwrap();
+        assert_close(6.0 / 36.0, dist.probability(7));
+        assert_close(7.0, dist.mean());
+        assert_close((35.0f64 / 6.0).sqrt(), dist.std_dev());
+        assert_close(1.0 / 36.0, dist.at_most(2));
+        assert_close(3.0 / 36.0, dist.at_least(11));
+        assert_eq!(
+            Some((12, 1.0)),
+            dist.cdf().last().map(|(t, p)| (t, p.round()))
+        );
+
+        let dist = Roller::new("(1d4 + 2)[fire] * 2 - 1d2 : reason")
+            .unwrap()
+            .distribution()
+            .unwrap();
+        assert_eq!((4, 11), (dist.get_min(), dist.get_max()));
+        assert_close(7.5, dist.mean());
+
+        let dist = Roller::new("3d6 * 1.5").unwrap().distribution().unwrap();
+        assert_eq!((4, 27), (dist.get_min(), dist.get_max()));
+        assert_close(1.0 / 216.0, dist.probability(4));
+        // the float is kept until the end, 3.5 + 0.5 gives 4
+        let dist = Roller::new("(1d6 * 1.5 + 0.5) * 2")
+            .unwrap()
+            .distribution()
+            .unwrap();
+        assert_eq!((4, 19), (dist.get_min(), dist.get_max()));
+        assert_close(1.0 / 6.0, dist.probability(7));
+        assert_close(0.0, dist.probability(6));
+        // an exact division keeps the fraction, rounded at the end
+        let arithmetic = Arithmetic {
+            exact_division: true,
+            rounding: FloatRounding::Ceil,
+        };
+        let dist = Roller::new("(1d6 * 1/3) * 3")
+            .unwrap()
+            .with_arithmetic(arithmetic)
+            .distribution()
+            .unwrap();
+        assert_same(&Roller::new("1d6").unwrap().distribution().unwrap(), &dist);
+        let dist = Roller::new("1d6 / 4")
+            .unwrap()
+            .with_arithmetic(arithmetic)
+            .distribution()
+            .unwrap();
+        assert_eq!((1, 2), (dist.get_min(), dist.get_max()));
+        assert_close(4.0 / 6.0, dist.probability(1));
+
+        let dist = Roller::new("(1d6 + 1)^+3").unwrap().distribution().unwrap();
+        assert_eq!((6, 21), (dist.get_min(), dist.get_max()));
+        assert_close(
+            Roller::new("3d6").unwrap().distribution().unwrap().mean() + 3.0,
+            dist.mean(),
+        );
+
+        let dist = Roller::new("1d20 / 1d2").unwrap().distribution().unwrap();
+        assert_eq!((0, 20), (dist.get_min(), dist.get_max()));
+
+        assert!(matches!(
+            Roller::new("(1d6)^3").unwrap().distribution(),
+            Err(RollError::NoSingleTotal { .. })
+        ));
+        assert_eq!(
+            Err(RollError::DivideByZero {
+                span: Span::new(6, 15)
+            }),
+            Roller::new("1d6 / (1d3 - 2)").unwrap().distribution()
+        );
+        assert!(matches!(
+            Roller::new("100d100 K50").unwrap().distribution(),
+            Err(RollError::TooComplex { .. })
+        ));
+        assert!(matches!(
+            Roller::new("7d10 e10 r1").unwrap().distribution(),
+            Err(RollError::TooComplex { .. })
+        ));
+    }
+
+    #[test]
+    fn distribution_depth_test() {
+        // the same totals as the roller and the analysis
+        let roller = Roller::new("1d6 ie1").unwrap();
+        let dist = roller.distribution().unwrap();
+        let analysis = roller.analyze().unwrap();
+        assert_eq!((101, 606), (dist.get_min(), dist.get_max()));
+        assert_eq!(
+            (analysis.get_min(), analysis.get_max()),
+            (dist.get_min(), dist.get_max())
+        );
+        assert_eq!(
+            101,
+            roller
+                .roll_with_source(&mut MinSource)
+                .unwrap()
+                .as_single()
+                .unwrap()
+                .get_total()
+        );
+
+        let limits = RollerLimits {
+            max_explosions: 3,
+            ..Default::default()
+        };
+        let dist = roller.with_limits(limits).distribution().unwrap();
+        assert_eq!((4, 24), (dist.get_min(), dist.get_max()));
+    }
+
+    #[test]
+    fn distribution_validation_test() {
+        let dist = |input: &str| Roller::new(input).unwrap().distribution();
+
+        // the minimum of the divisor is -2, zero is found in its support
+        assert_eq!(
+            Err(RollError::DivideByZero {
+                span: Span::new(5, 14)
+            }),
+            dist("10 / (1d6 - 3)")
+        );
+        // the divisor is odd, never zero
+        let res = dist("12 / (1d6 * 2 - 7)").unwrap();
+        assert_eq!((-12, 12), (res.get_min(), res.get_max()));
+
+        // the limits of the roller are checked without rolling
+        assert!(matches!(
+            dist("1d6 + 6000d6"),
+            Err(RollError::TooManyDice { .. })
+        ));
+        assert!(matches!(dist("1d0"), Err(RollError::ZeroSides { .. })));
+        assert!(matches!(
+            dist("(1d6)^+0"),
+            Err(RollError::InvalidRepetition { .. })
+        ));
+        assert_eq!(
+            Err(RollError::InvalidLabelOperation {
+                span: Span::new(5, 11)
+            }),
+            dist("10 / 1d4[a]")
+        );
+    }
+}
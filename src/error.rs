@@ -1,44 +1,646 @@
 use std::{error::Error, fmt::Display};
 
-use crate::parser::*;
+use crate::parser::Rule;
 
 /// Result type used accross the library
 pub type Result<T> = std::result::Result<T, RollError>;
 
+/// Byte range of the input where an error occurred, `end` being excluded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte offset of the start of the span
+    pub start: usize,
+    /// Byte offset of the end of the span, excluded
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a `Span` from `start` to `end`, `end` being excluded.
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Get the span as a range, usable to slice the input.
+    pub fn as_range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    pub(crate) fn join(self, other: Span) -> Self {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub(crate) fn offset(self, offset: usize) -> Self {
+        Span::new(self.start + offset, self.end + offset)
+    }
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        Span::new(span.start(), span.end())
+    }
+}
+
 /// The error reported
-#[derive(Debug)]
+///
+/// Every error that comes from the expression carries the [`Span`] of the input where it
+/// occurred, see [`RollError::span()`]. [`RollError::hint()`] may give a suggestion to fix it.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RollError {
-    /// Error while parsing the expression, emitted by `pest`
-    ParseError(Box<pest::error::Error<Rule>>),
-    /// Any other error while walking the AST, the String contains an explaination of what happened
-    ParamError(String),
+    /// The expression is not valid
+    Syntax {
+        /// Where the parsing failed
+        span: Span,
+        /// What was found instead, `None` at the end of the input
+        found: Option<String>,
+        /// Readable names of what was expected at this position
+        expected: Vec<&'static str>,
+        /// A suggestion to fix the expression
+        hint: Option<String>,
+    },
+    /// More dice than allowed in one roll
+    TooManyDice {
+        /// The number of dice
+        span: Span,
+        /// Number of dice asked
+        requested: u64,
+        /// Maximum number of dice allowed
+        max: u64,
+    },
+    /// Dice with more sides than allowed
+    TooManySides {
+        /// The number of sides
+        span: Span,
+        /// Number of sides asked
+        requested: u64,
+        /// Maximum number of sides allowed
+        max: u64,
+    },
+    /// Dice with 0 side
+    ZeroSides {
+        /// The number of sides
+        span: Span,
+    },
+    /// Division by a value which is zero
+    DivideByZero {
+        /// The divisor
+        span: Span,
+    },
+    /// A number or a computed total doesn't fit in the integer types
+    Overflow {
+        /// The number or the operation which overflows
+        span: Span,
+    },
+    /// Two labelled values are multiplied, or a value is divided by a labelled value, see
+    /// [`crate::SingleRollResult::get_label_totals()`]
+    InvalidLabelOperation {
+        /// The product, or the divisor
+        span: Span,
+    },
+    /// A decimal number has too many decimals to be an exact fraction
+    TooManyDecimals {
+        /// The decimal number
+        span: Span,
+        /// Maximum number of decimals
+        max: usize,
+    },
+    /// A keep or drop option asks for more dice than rolled
+    NotEnoughDice {
+        /// The dice expression
+        span: Span,
+        /// Number of dice to keep or drop
+        requested: usize,
+        /// Number of dice rolled
+        available: usize,
+    },
+    /// A repetition asks to repeat 0 times
+    InvalidRepetition {
+        /// The number of repetitions
+        span: Span,
+    },
+    /// A list of labels in brackets doesn't have one non-empty label per repetition
+    InvalidRepetitionLabels {
+        /// The list of labels
+        span: Span,
+        /// Number of non-empty labels given
+        labels: usize,
+        /// Number of repetitions
+        repetitions: u64,
+    },
+    /// A repetition asks to repeat more than allowed
+    TooManyRepetitions {
+        /// The number of repetitions
+        span: Span,
+        /// Number of repetitions asked
+        requested: u64,
+        /// Maximum number of repetitions allowed
+        max: u64,
+    },
+    /// The evaluation rolled more dice than allowed, explosions, rerolls and repetitions included
+    TooManyDiceRolled {
+        /// The dice expression that went over the limit
+        span: Span,
+        /// Maximum number of dice allowed
+        max: u64,
+    },
+    /// Parenthesis are nested deeper than allowed
+    TooDeep {
+        /// The parenthesis that went over the limit
+        span: Span,
+        /// Maximum nesting allowed
+        max: usize,
+    },
+    /// An inline roll is opened but never closed, see [`crate::InlineRoller`]
+    UnclosedInlineRoll {
+        /// The opening delimiter, in the text
+        span: Span,
+        /// The missing delimiter
+        delimiter: String,
+    },
+    /// The dice roll source has no more values to give, see [`crate::ScriptedSource`]
+    SourceExhausted {
+        /// The dice expression being rolled
+        span: Span,
+        /// Number of values given before running out
+        rolled: usize,
+    },
+    /// The dice roll source gave a value which is not between 1 and the number of sides
+    InvalidDieValue {
+        /// The dice expression being rolled
+        span: Span,
+        /// The value given
+        value: u64,
+        /// Number of sides of the die
+        sides: u64,
+    },
+    /// The expression doesn't roll the dice recorded in the transcript, see [`crate::Roller::replay()`]
+    ReplayMismatch {
+        /// The dice expression being rolled
+        span: Span,
+        /// Index of the die in the transcript
+        index: usize,
+        /// Number of sides of the die rolled by the expression
+        requested_sides: u64,
+        /// Number of sides of the die in the transcript, `None` if the transcript has no more dice
+        recorded_sides: Option<u64>,
+    },
+    /// The expression rolled less dice than recorded in the transcript
+    ReplayUnused {
+        /// Number of dice rolled by the expression
+        used: usize,
+        /// Number of dice in the transcript
+        recorded: usize,
+    },
+    /// The expression has too many outcomes to compute its distribution, see
+    /// [`crate::Roller::distribution()`]
+    TooComplex {
+        /// The part of the expression that went over the limit
+        span: Span,
+    },
+    /// The expression has no single total, like a repetition not summed with `^+`
+    NoSingleTotal {
+        /// The expression
+        span: Span,
+    },
+    /// A dice of the expression doesn't count successes, see
+    /// [`crate::Roller::expected_successes()`]
+    NotSuccessCount {
+        /// The dice, or the expression if it has no dice
+        span: Span,
+    },
+    /// A transcript can't be read from a string, see [`crate::RollTranscript`]
+    InvalidTranscript(String),
+    /// A template doesn't have exactly one parameter, see [`crate::RollTemplate`]
+    InvalidTemplate(String),
+    /// A server seed is not 64 hexadecimal characters, see [`crate::fair::ServerSeed`]
+    #[cfg(feature = "fair")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fair")))]
+    InvalidSeed,
+    /// An [`crate::InlineRoller`] can't have empty delimiters
+    EmptyDelimiter,
+    /// The confidence level of a [`crate::SimulationOptions`] is not between 0 and 1 exclusive
+    InvalidConfidence,
+    /// A [`crate::SimulationOptions`] asks for 0 iterations
+    NoIterations,
+    /// A helper needs the result of a single roll, not a repeated one
+    NotSingleRoll,
+    /// A helper needs the result of only one dice expression, like `4d6`
+    NotPlainRoll,
+    /// A helper is asked to roll zero dice
+    ZeroDice,
+    /// The element given to `helpers::compute_cde` is unknown
+    UnknownElement(String),
 }
 
-impl Display for RollError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl RollError {
+    /// Get the position in the input where the error occurred, if it comes from the input.
+    pub fn span(&self) -> Option<Span> {
         match self {
-            RollError::ParseError(e) => write!(f, "{}", e),
-            RollError::ParamError(e) => write!(f, "{}", e),
+            RollError::Syntax { span, .. }
+            | RollError::TooManyDice { span, .. }
+            | RollError::TooManySides { span, .. }
+            | RollError::ZeroSides { span }
+            | RollError::DivideByZero { span }
+            | RollError::Overflow { span }
+            | RollError::InvalidLabelOperation { span }
+            | RollError::TooManyDecimals { span, .. }
+            | RollError::NotEnoughDice { span, .. }
+            | RollError::InvalidRepetition { span }
+            | RollError::InvalidRepetitionLabels { span, .. }
+            | RollError::TooManyRepetitions { span, .. }
+            | RollError::TooManyDiceRolled { span, .. }
+            | RollError::TooDeep { span, .. }
+            | RollError::UnclosedInlineRoll { span, .. }
+            | RollError::SourceExhausted { span, .. }
+            | RollError::InvalidDieValue { span, .. }
+            | RollError::ReplayMismatch { span, .. }
+            | RollError::TooComplex { span }
+            | RollError::NoSingleTotal { span }
+            | RollError::NotSuccessCount { span } => Some(*span),
+            RollError::ReplayUnused { .. }
+            | RollError::InvalidTranscript(_)
+            | RollError::InvalidTemplate(_)
+            | RollError::EmptyDelimiter
+            | RollError::InvalidConfidence
+            | RollError::NoIterations
+            | RollError::NotSingleRoll
+            | RollError::NotPlainRoll
+            | RollError::ZeroDice
+            | RollError::UnknownElement(_) => None,
+            #[cfg(feature = "fair")]
+            RollError::InvalidSeed => None,
         }
     }
-}
 
-impl Error for RollError {}
+    /// Get a suggestion to fix the expression, if any.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            RollError::Syntax { hint, .. } => hint.clone(),
+            RollError::TooManyDice { max, .. } => {
+                Some(format!("roll at most {} dice at once", max))
+            }
+            RollError::TooManySides { max, .. } => {
+                Some(format!("use dice with at most {} sides", max))
+            }
+            RollError::NotEnoughDice { available, .. } => Some(format!(
+                "keep or drop at most {} {}",
+                available,
+                if *available > 1 { "dice" } else { "die" }
+            )),
+            RollError::InvalidRepetition { .. } => Some("repeat at least once".to_owned()),
+            RollError::InvalidRepetitionLabels { repetitions, .. } => {
+                Some(format!("give {} labels separated by commas", repetitions))
+            }
+            RollError::TooManyRepetitions { max, .. } => {
+                Some(format!("repeat at most {} times", max))
+            }
+            RollError::TooManyDiceRolled { .. } => {
+                Some("roll fewer dice, or fewer explosions and rerolls".to_owned())
+            }
+            RollError::TooDeep { max, .. } => {
+                Some(format!("use at most {} levels of parenthesis", max))
+            }
+            RollError::UnclosedInlineRoll { delimiter, .. } => {
+                Some(format!("add `{}` at the end of the expression", delimiter))
+            }
+            RollError::Overflow { .. } => Some("use smaller numbers".to_owned()),
+            RollError::TooManyDecimals { max, .. } => Some(format!("use at most {} decimals", max)),
+            RollError::InvalidLabelOperation { .. } => {
+                Some("label the whole product or quotient, like `(1d6 * 1d4)[fire]`".to_owned())
+            }
+            RollError::TooComplex { .. } => {
+                Some("use fewer dice, or a lower explosion or reroll depth".to_owned())
+            }
+            RollError::NoSingleTotal { .. } => Some("sum the repetitions with `^+`".to_owned()),
+            RollError::NotSuccessCount { .. } => Some("add a target with `t`".to_owned()),
+            RollError::InvalidConfidence => Some("use a confidence level like 0.95".to_owned()),
+            RollError::NoIterations => Some("roll at least once".to_owned()),
+            RollError::UnknownElement(_) => {
+                Some("use `fire`, `earth`, `metal`, `water` or `wood`".to_owned())
+            }
+            RollError::ZeroSides { .. }
+            | RollError::DivideByZero { .. }
+            | RollError::SourceExhausted { .. }
+            | RollError::InvalidDieValue { .. }
+            | RollError::ReplayMismatch { .. }
+            | RollError::ReplayUnused { .. }
+            | RollError::InvalidTranscript(_)
+            | RollError::InvalidTemplate(_)
+            | RollError::EmptyDelimiter
+            | RollError::NotSingleRoll
+            | RollError::NotPlainRoll
+            | RollError::ZeroDice => None,
+            #[cfg(feature = "fair")]
+            RollError::InvalidSeed => None,
+        }
+    }
+
+    // Errors of an expression embedded in a text are reported relatively to the text
+    pub(crate) fn offset(self, offset: usize) -> Self {
+        let mut e = self;
+        match &mut e {
+            RollError::Syntax { span, .. }
+            | RollError::TooManyDice { span, .. }
+            | RollError::TooManySides { span, .. }
+            | RollError::ZeroSides { span }
+            | RollError::DivideByZero { span }
+            | RollError::Overflow { span }
+            | RollError::InvalidLabelOperation { span }
+            | RollError::TooManyDecimals { span, .. }
+            | RollError::NotEnoughDice { span, .. }
+            | RollError::InvalidRepetition { span }
+            | RollError::InvalidRepetitionLabels { span, .. }
+            | RollError::TooManyRepetitions { span, .. }
+            | RollError::TooManyDiceRolled { span, .. }
+            | RollError::TooDeep { span, .. }
+            | RollError::UnclosedInlineRoll { span, .. }
+            | RollError::SourceExhausted { span, .. }
+            | RollError::InvalidDieValue { span, .. }
+            | RollError::ReplayMismatch { span, .. }
+            | RollError::TooComplex { span }
+            | RollError::NoSingleTotal { span }
+            | RollError::NotSuccessCount { span } => *span = span.offset(offset),
+            RollError::ReplayUnused { .. }
+            | RollError::InvalidTranscript(_)
+            | RollError::InvalidTemplate(_)
+            | RollError::EmptyDelimiter
+            | RollError::InvalidConfidence
+            | RollError::NoIterations
+            | RollError::NotSingleRoll
+            | RollError::NotPlainRoll
+            | RollError::ZeroDice
+            | RollError::UnknownElement(_) => (),
+            #[cfg(feature = "fair")]
+            RollError::InvalidSeed => (),
+        }
+        e
+    }
 
-impl From<pest::error::Error<Rule>> for RollError {
-    fn from(e: pest::error::Error<Rule>) -> Self {
-        RollError::ParseError(Box::new(e))
+    // Errors coming from a dice roll source don't know which dice expression was being rolled
+    pub(crate) fn with_source_span(self, dice_span: Span) -> Self {
+        let mut e = self;
+        match &mut e {
+            RollError::SourceExhausted { span, .. }
+            | RollError::InvalidDieValue { span, .. }
+            | RollError::ReplayMismatch { span, .. } => *span = dice_span,
+            _ => (),
+        }
+        e
+    }
+
+    pub(crate) fn from_pest(input: &str, e: pest::error::Error<Rule>) -> Self {
+        let pos = match e.location {
+            pest::error::InputLocation::Pos(pos) => pos,
+            pest::error::InputLocation::Span((start, _)) => start,
+        };
+        let found = input[pos..].chars().next();
+        let span = Span::new(pos, pos + found.map(char::len_utf8).unwrap_or(0));
+        let mut expected = match e.variant {
+            pest::error::ErrorVariant::ParsingError { positives, .. } => positives
+                .iter()
+                .map(rule_to_str)
+                .fold(Vec::new(), |mut acc, s| {
+                    if !acc.contains(&s) {
+                        acc.push(s);
+                    }
+                    acc
+                }),
+            pest::error::ErrorVariant::CustomError { .. } => Vec::new(),
+        };
+        expected.sort_unstable();
+        RollError::Syntax {
+            span,
+            found: found.map(|c| c.to_string()),
+            expected,
+            hint: syntax_hint(input, pos),
+        }
+    }
+}
+
+fn rule_to_str(rule: &Rule) -> &'static str {
+    match rule {
+        Rule::number | Rule::float | Rule::fraction | Rule::integer | Rule::nb_dice => "a number",
+        Rule::dice | Rule::roll => "a dice",
+        Rule::fudge => "`F`",
+        Rule::explode
+        | Rule::i_explode
+        | Rule::reroll
+        | Rule::i_reroll
+        | Rule::keep_hi
+        | Rule::keep_lo
+        | Rule::drop_hi
+        | Rule::drop_lo => "an option",
+        Rule::target | Rule::double_target | Rule::failure | Rule::target_enum => "a target",
+        Rule::add | Rule::sub | Rule::mul | Rule::div => "an operator",
+        Rule::block_expr | Rule::repeated_expr | Rule::expr => "an expression",
+        Rule::label | Rule::label_text => "a label",
+        Rule::reason => "a reason",
+        Rule::sort => "`#`",
+        Rule::descending => "`>`",
+        Rule::EOI => "the end of the expression",
+        // silent rules are never reported
+        _ => "an expression",
     }
 }
 
-impl From<&str> for RollError {
-    fn from(e: &str) -> Self {
-        RollError::ParamError(e.to_string())
+// Look for common mistakes around the position where the parsing failed.
+fn syntax_hint(input: &str, pos: usize) -> Option<String> {
+    // an option letter can be valid, and the error reported on the next character
+    let word_start = input[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_ascii_alphabetic())
+        .last()
+        .map(|(idx, _)| idx)
+        .unwrap_or(pos);
+    let before = &input[..word_start];
+    let rest = &input[word_start..];
+    let word: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    let number_before: String = before
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let lower = word.to_lowercase();
+
+    // other dice rollers' syntax for keep and drop
+    let options = [("kh", "K"), ("kl", "k"), ("dh", "D"), ("dl", "d")];
+    for (other, ours) in options.iter() {
+        if let Some(n) = lower.strip_prefix(other) {
+            if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) {
+                return Some(format!("did you mean `{}{}`?", ours, n));
+            }
+        }
+    }
+
+    // `2x6` or `2w6` for `2d6`
+    if let Some(sides) = lower.strip_prefix('x').or_else(|| lower.strip_prefix('w')) {
+        if !number_before.is_empty()
+            && !sides.is_empty()
+            && sides.chars().all(|c| c.is_ascii_digit())
+        {
+            return Some(format!("did you mean `{}d{}`?", number_before, sides));
+        }
+    }
+    if rest.starts_with('x') || rest.starts_with('×') {
+        return Some("did you mean `*`?".to_owned());
+    }
+
+    let open = input.matches('(').count();
+    let close = input.matches(')').count();
+    if open > close {
+        return Some("missing `)`".to_owned());
+    } else if close > open {
+        return Some("remove the extra `)`".to_owned());
     }
+
+    let before = input[..pos].trim_end();
+    if before.ends_with(['d', 'D']) && input[pos..].trim().is_empty() {
+        return Some(format!(
+            "missing number of sides, did you mean `{}6`?",
+            before
+        ));
+    }
+    None
 }
 
-impl From<String> for RollError {
-    fn from(e: String) -> Self {
-        Self::ParamError(e)
+impl Display for RollError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RollError::Syntax {
+                found, expected, ..
+            } => {
+                match found {
+                    Some(found) => write!(f, "Unexpected `{}`", found)?,
+                    None => write!(f, "Unexpected end of expression")?,
+                }
+                if let Some((last, first)) = expected.split_last() {
+                    if first.is_empty() {
+                        write!(f, ", expected {}", last)?;
+                    } else {
+                        write!(f, ", expected {} or {}", first.join(", "), last)?;
+                    }
+                }
+            }
+            RollError::TooManyDice { requested, max, .. } => write!(
+                f,
+                "Exceed maximum allowed number of dices ({} > {})",
+                requested, max
+            )?,
+            RollError::TooManySides { requested, max, .. } => write!(
+                f,
+                "Dice can't have more than {} sides ({} asked)",
+                max, requested
+            )?,
+            RollError::ZeroSides { .. } => write!(f, "Dice can't have 0 sides")?,
+            RollError::DivideByZero { .. } => write!(f, "Can't divide by zero")?,
+            RollError::Overflow { .. } => write!(f, "Number too big")?,
+            RollError::TooManyDecimals { .. } => write!(f, "Too many decimals")?,
+            RollError::InvalidLabelOperation { .. } => write!(
+                f,
+                "Can't multiply two labelled values or divide by a labelled value"
+            )?,
+            RollError::NotEnoughDice {
+                requested,
+                available,
+                ..
+            } => write!(
+                f,
+                "Not enough dice to keep or drop ({} asked, {} rolled)",
+                requested, available
+            )?,
+            RollError::InvalidRepetition { .. } => write!(f, "Can't repeat 0 times or negatively")?,
+            RollError::InvalidRepetitionLabels {
+                labels,
+                repetitions,
+                ..
+            } => write!(
+                f,
+                "Expected one label per repetition ({} given, {} repetitions)",
+                labels, repetitions
+            )?,
+            RollError::TooManyRepetitions { requested, max, .. } => write!(
+                f,
+                "Exceed maximum allowed number of repetitions ({} > {})",
+                requested, max
+            )?,
+            RollError::TooManyDiceRolled { max, .. } => write!(
+                f,
+                "Exceed maximum allowed number of dices rolled in total ({})",
+                max
+            )?,
+            RollError::TooDeep { max, .. } => {
+                write!(f, "Exceed maximum nesting of parenthesis ({})", max)?
+            }
+            RollError::UnclosedInlineRoll { delimiter, .. } => {
+                write!(f, "Missing `{}` to close the inline roll", delimiter)?
+            }
+            RollError::SourceExhausted { rolled, .. } => write!(
+                f,
+                "The dice roll source has no more values ({} given)",
+                rolled
+            )?,
+            RollError::InvalidDieValue { value, sides, .. } => write!(
+                f,
+                "The dice roll source gave {} for a die of {} sides",
+                value, sides
+            )?,
+            RollError::ReplayMismatch {
+                index,
+                requested_sides,
+                recorded_sides: Some(recorded_sides),
+                ..
+            } => write!(
+                f,
+                "The transcript doesn't match: die #{} has {} sides instead of {}",
+                index + 1,
+                recorded_sides,
+                requested_sides
+            )?,
+            RollError::ReplayMismatch {
+                index,
+                recorded_sides: None,
+                ..
+            } => write!(
+                f,
+                "The transcript doesn't match: it has only {} dice, the expression rolls more",
+                index
+            )?,
+            RollError::ReplayUnused { used, recorded } => write!(
+                f,
+                "The transcript doesn't match: it has {} dice, the expression rolls only {}",
+                recorded, used
+            )?,
+            RollError::TooComplex { .. } => {
+                write!(f, "Too many outcomes to compute the distribution")?
+            }
+            RollError::NoSingleTotal { .. } => write!(f, "The expression has several totals")?,
+            RollError::NotSuccessCount { .. } => write!(f, "The dice don't count successes")?,
+            RollError::InvalidTranscript(e) => write!(f, "Invalid transcript: {}", e)?,
+            RollError::InvalidTemplate(e) => write!(f, "Invalid template: {}", e)?,
+            #[cfg(feature = "fair")]
+            RollError::InvalidSeed => write!(f, "A seed must be 64 hexadecimal characters")?,
+            RollError::EmptyDelimiter => write!(f, "Delimiters can't be empty")?,
+            RollError::InvalidConfidence => {
+                write!(f, "The confidence level must be between 0 and 1 exclusive")?
+            }
+            RollError::NoIterations => write!(f, "The simulation has no iteration")?,
+            RollError::NotSingleRoll => write!(f, "Not a single roll result")?,
+            RollError::NotPlainRoll => write!(f, "Should have only one roll of dice")?,
+            RollError::ZeroDice => write!(f, "Number can't be zero")?,
+            RollError::UnknownElement(e) => write!(f, "Unknown element `{}`", e)?,
+        }
+        if let Some(hint) = self.hint() {
+            write!(f, " ({})", hint)?;
+        }
+        Ok(())
     }
 }
+
+impl Error for RollError {}
@@ -0,0 +1,279 @@
+//! Provably fair rolls, with a commit/reveal scheme.
+//!
+//! 1. The server draws a secret [`ServerSeed`] and publishes its commitment, the SHA-256 of the
+//!    seed, before any roll.
+//! 2. The player gives a client seed, and each roll uses a new nonce (a counter for example).
+//! 3. The dice come from a [`FairSource`] built from the server seed, the client seed and the
+//!    nonce, given to [`crate::Roller::roll_with_source()`].
+//! 4. Later, the server reveals its seed, and anyone can check the commitment and the results with
+//!    [`verify_fair_roll()`], given a roller with the same expression, limits and arithmetic.
+//!
+//! The server can't choose the dice because it's committed to its seed before knowing the client
+//! seed, and the player can't predict the dice because the server seed is secret until revealed.
+//!
+//! # Algorithm
+//!
+//! - The server seed is 32 bytes, written as 64 hexadecimal characters. The commitment is the
+//!   SHA-256 of these 32 bytes, written in hexadecimal.
+//! - The dice are drawn from a stream of 64 bits numbers. Block number `i` (starting at 0) of the
+//!   stream is `SHA-256(server seed bytes || client seed as UTF-8 || nonce as 8 bytes big endian
+//!   || i as 8 bytes big endian)`, and each block gives four numbers, reading the 32 bytes as
+//!   big endian unsigned integers of 8 bytes.
+//! - To roll a die of `n` sides, the next number `x` of the stream is taken: if `x` is lower than
+//!   `2^64 - (2^64 mod n)`, the die is `x mod n + 1`. Otherwise, `x` is discarded and the next
+//!   number is taken, so every side has the same probability.
+//! - The dice are rolled in the order the expression requests them, which is the order given by
+//!   [`crate::RecordingSource`].
+//!
+//! # Examples
+//!
+//! ```
+//! use caith::{fair::{verify_fair_roll, ServerSeed}, Roller};
+//!
+//! // before the roll, the server publishes the commitment
+//! let seed = ServerSeed::new();
+//! let commitment = seed.commitment();
+//!
+//! let roller = Roller::new("1d20 + 5").unwrap();
+//! let res = roller
+//!     .roll_with_source(&mut seed.source("player's seed", 1))
+//!     .unwrap();
+//!
+//! // later, the server reveals its seed and anyone can check the roll
+//! let revealed = seed.reveal();
+//! assert!(
+//!     verify_fair_roll(&roller, &commitment, &revealed, "player's seed", 1, &res).unwrap()
+//! );
+//! ```
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{Result, RollError},
+    DiceRollSource, RollResult, Roller,
+};
+
+const SEED_LEN: usize = 32;
+
+/// The secret seed of the server. See the [module documentation](self).
+#[derive(Clone, PartialEq, Eq)]
+pub struct ServerSeed {
+    seed: [u8; SEED_LEN],
+}
+
+impl std::fmt::Debug for ServerSeed {
+    // don't leak the secret in logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerSeed")
+            .field("commitment", &self.commitment())
+            .finish()
+    }
+}
+
+impl Default for ServerSeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerSeed {
+    /// Draw a new random seed with `rand::thread_rng()`
+    pub fn new() -> Self {
+        let mut seed = [0; SEED_LEN];
+        rand::thread_rng().fill_bytes(&mut seed);
+        ServerSeed { seed }
+    }
+
+    /// Use the given bytes as seed
+    pub fn from_bytes(seed: [u8; SEED_LEN]) -> Self {
+        ServerSeed { seed }
+    }
+
+    /// Read a seed written as 64 hexadecimal characters, like the one given by
+    /// [`ServerSeed::reveal()`]
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = hex.as_bytes();
+        // `from_str_radix` would also accept a sign
+        if bytes.len() != SEED_LEN * 2 || !bytes.iter().all(u8::is_ascii_hexdigit) {
+            return Err(RollError::InvalidSeed);
+        }
+        let mut seed = [0; SEED_LEN];
+        for (byte, digits) in seed.iter_mut().zip(bytes.chunks(2)) {
+            let digits = std::str::from_utf8(digits).map_err(|_| RollError::InvalidSeed)?;
+            *byte = u8::from_str_radix(digits, 16).map_err(|_| RollError::InvalidSeed)?;
+        }
+        Ok(ServerSeed { seed })
+    }
+
+    /// Get the commitment to publish before rolling: the SHA-256 of the seed, in hexadecimal
+    pub fn commitment(&self) -> String {
+        to_hex(&Sha256::digest(self.seed))
+    }
+
+    /// Get the seed in hexadecimal, to publish once the rolls using it are done
+    pub fn reveal(&self) -> String {
+        to_hex(&self.seed)
+    }
+
+    /// Get the dice roll source for a client seed and a nonce
+    pub fn source(&self, client_seed: &str, nonce: u64) -> FairSource {
+        FairSource::new(self, client_seed, nonce)
+    }
+}
+
+/// Dice derived from a server seed, a client seed and a nonce. See the
+/// [module documentation](self) for the algorithm.
+#[derive(Debug, Clone)]
+pub struct FairSource {
+    server_seed: ServerSeed,
+    client_seed: String,
+    nonce: u64,
+    block: u64,
+    numbers: Vec<u64>,
+}
+
+impl FairSource {
+    /// Create the source for a server seed, a client seed and a nonce
+    pub fn new(server_seed: &ServerSeed, client_seed: &str, nonce: u64) -> Self {
+        FairSource {
+            server_seed: server_seed.clone(),
+            client_seed: client_seed.to_owned(),
+            nonce,
+            block: 0,
+            numbers: Vec::new(),
+        }
+    }
+
+    fn next_number(&mut self) -> u64 {
+        if self.numbers.is_empty() {
+            let hash = Sha256::new()
+                .chain_update(self.server_seed.seed)
+                .chain_update(self.client_seed.as_bytes())
+                .chain_update(self.nonce.to_be_bytes())
+                .chain_update(self.block.to_be_bytes())
+                .finalize();
+            self.block += 1;
+            // numbers are popped from the end
+            self.numbers = hash
+                .chunks(8)
+                .rev()
+                .map(|chunk| {
+                    let mut bytes = [0; 8];
+                    bytes.copy_from_slice(chunk);
+                    u64::from_be_bytes(bytes)
+                })
+                .collect();
+        }
+        self.numbers.pop().unwrap()
+    }
+}
+
+impl DiceRollSource for FairSource {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        // 2^64 - (2^64 mod sides), computed without overflowing; 0 stands for 2^64
+        let limit = 0_u64.wrapping_sub(0_u64.wrapping_sub(sides) % sides);
+        loop {
+            let x = self.next_number();
+            if limit == 0 || x < limit {
+                return x % sides + 1;
+            }
+        }
+    }
+}
+
+/// Check a roll once the server seed is revealed.
+///
+/// Returns `Ok(false)` if the revealed seed doesn't match the commitment, or if `roller` doesn't
+/// give `result` with the dice of the seeds and the nonce. The roller must have the limits and
+/// the arithmetic of the original roll. Returns an error if the revealed seed is not 64
+/// hexadecimal characters, or if the expression can't be evaluated.
+pub fn verify_fair_roll(
+    roller: &Roller,
+    commitment: &str,
+    revealed_seed: &str,
+    client_seed: &str,
+    nonce: u64,
+    result: &RollResult,
+) -> Result<bool> {
+    let seed = ServerSeed::from_hex(revealed_seed)?;
+    if !seed.commitment().eq_ignore_ascii_case(commitment.trim()) {
+        return Ok(false);
+    }
+    let expected = roller.roll_with_source(&mut seed.source(client_seed, nonce))?;
+    Ok(&expected == result)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Arithmetic, FloatRounding};
+
+    #[test]
+    fn fair_test() {
+        let seed = ServerSeed::from_bytes([7; 32]);
+        assert_eq!(
+            "0707070707070707070707070707070707070707070707070707070707070707",
+            seed.reveal()
+        );
+        assert_eq!(
+            seed,
+            ServerSeed::from_hex(&seed.reveal().to_uppercase()).unwrap()
+        );
+        assert_eq!(Err(RollError::InvalidSeed), ServerSeed::from_hex("07"));
+        let signed = format!("+f{}", &seed.reveal()[2..]);
+        assert_eq!(Err(RollError::InvalidSeed), ServerSeed::from_hex(&signed));
+
+        // the algorithm is part of the API: the same seeds must always give the same dice, these
+        // values are computed independently from the documented algorithm
+        assert_eq!(
+            "4bb06f8e4e3a7715d201d573d0aa423762e55dabd61a2c02278fa56cc6d294e0",
+            seed.commitment()
+        );
+        let mut source = seed.source("client", 42);
+        let dice = (0..6)
+            .map(|_| source.roll_single_die(20))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1, 17, 15, 18, 16, 8], dice);
+
+        let commitment = seed.commitment();
+        let res = Roller::new("4d6 K3")
+            .unwrap()
+            .roll_with_source(&mut seed.source("client", 1))
+            .unwrap();
+        let verify = |expr: &str, commitment: &str, nonce| {
+            let roller = Roller::new(expr).unwrap();
+            verify_fair_roll(&roller, commitment, &seed.reveal(), "client", nonce, &res).unwrap()
+        };
+        assert!(verify("4d6 K3", &commitment, 1));
+        assert!(!verify(
+            "4d6 K3",
+            &ServerSeed::from_bytes([8; 32]).commitment(),
+            1
+        ));
+        assert!(!verify("4d6 K3", &commitment, 2));
+        assert!(!verify("4d6 k3", &commitment, 1));
+
+        // the roll is verified with the arithmetic it was rolled with
+        let exact = Arithmetic {
+            exact_division: true,
+            rounding: FloatRounding::Nearest,
+        };
+        let roller = Roller::new("(1d20 / 3) * 3")
+            .unwrap()
+            .with_arithmetic(exact);
+        let res = roller
+            .roll_with_source(&mut seed.source("client", 42))
+            .unwrap();
+        // the first die of this nonce is a 1, which the integer division would lose
+        assert_eq!(1, res.as_single().unwrap().get_total());
+        let revealed = seed.reveal();
+        assert!(verify_fair_roll(&roller, &commitment, &revealed, "client", 42, &res).unwrap());
+        let default = Roller::new("(1d20 / 3) * 3").unwrap();
+        assert!(!verify_fair_roll(&default, &commitment, &revealed, "client", 42, &res).unwrap());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    error::Result, parser::RollContext, DiceRollSource, FloatRounding, RollResult, Roller,
+};
+
+/// The value given to every die by [`Roller::roll_fixed()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FixedMode {
+    /// The expected value of the die, rounded when it's not a whole number, like 3.5 for a d6:
+    /// [`FloatRounding::Truncate`] and [`FloatRounding::Floor`] give 3,
+    /// [`FloatRounding::Ceil`] and [`FloatRounding::Nearest`] give 4
+    Average(FloatRounding),
+    /// The expected value of the die, rounded up and down in turn, starting with up, so `2d6`
+    /// gives 4 and 3 for a total of 7
+    AlternateAverage,
+    /// The highest value of the die
+    Maximized,
+    /// The lowest value of the die, 1
+    Minimized,
+}
+
+// Give the value of the mode to every die
+struct FixedSource {
+    mode: FixedMode,
+    // for `FixedMode::AlternateAverage`
+    round_up: bool,
+}
+
+impl DiceRollSource for FixedSource {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        match self.mode {
+            FixedMode::Maximized => sides,
+            FixedMode::Minimized => 1,
+            // the average of a die with an even number of sides is halfway between two values
+            FixedMode::Average(_) | FixedMode::AlternateAverage if sides % 2 == 1 => sides / 2 + 1,
+            FixedMode::Average(FloatRounding::Truncate | FloatRounding::Floor) => sides / 2,
+            FixedMode::Average(FloatRounding::Ceil | FloatRounding::Nearest) => sides / 2 + 1,
+            FixedMode::AlternateAverage => {
+                self.round_up = !self.round_up;
+                sides / 2 + if self.round_up { 1 } else { 0 }
+            }
+        }
+    }
+}
+
+impl Roller {
+    /// Evaluate the expression with a fixed value for every die instead of rolling it, like the
+    /// average damage of a monster or maximized dice on a critical hit.
+    ///
+    /// Options and operations apply as usual: with [`FixedMode::Maximized`], every die explodes,
+    /// up to the limits of the roller for indefinite explosions. The history shows the dice as
+    /// [`crate::RollHistory::Fixed`], between braces. Fudge dice keep their symbols.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{FixedMode, Roller};
+    ///
+    /// let roller = Roller::new("2d6 + 3").unwrap();
+    /// let res = roller.roll_fixed(FixedMode::AlternateAverage).unwrap();
+    /// assert_eq!("`{4, 3} + 3` = **10**", res.to_string());
+    ///
+    /// let res = roller.roll_fixed(FixedMode::Maximized).unwrap();
+    /// assert_eq!(15, res.as_single().unwrap().get_total());
+    /// ```
+    pub fn roll_fixed(&self, mode: FixedMode) -> Result<RollResult> {
+        let mut source = FixedSource {
+            mode,
+            round_up: false,
+        };
+        self.roll_in_context(
+            self.parse()?,
+            &mut RollContext::new(&mut source, self.get_limits())
+                .with_arithmetic(self.get_arithmetic())
+                .fixed(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollHistory;
+
+    fn total(input: &str, mode: FixedMode) -> i64 {
+        Roller::new(input)
+            .unwrap()
+            .roll_fixed(mode)
+            .unwrap()
+            .as_single()
+            .unwrap()
+            .get_total()
+    }
+
+    #[test]
+    fn fixed_test() {
+        use FixedMode::*;
+        assert_eq!(6, total("2d6", Average(FloatRounding::Truncate)));
+        assert_eq!(6, total("2d6", Average(FloatRounding::Floor)));
+        assert_eq!(8, total("2d6", Average(FloatRounding::Ceil)));
+        assert_eq!(8, total("2d6", Average(FloatRounding::Nearest)));
+        assert_eq!(7, total("2d6", AlternateAverage));
+        // 4 + 3 + 4, then 4 for the d8 and 11 for the d20
+        assert_eq!(26, total("3d6 + 1d8 + 1d20", AlternateAverage));
+        assert_eq!(27, total("3d11 * 1.5", Average(FloatRounding::Floor)));
+        assert_eq!(5, total("4d6 K1 + 2d1", Average(FloatRounding::Floor)));
+        assert_eq!(3, total("3d6", Minimized));
+        // every die explodes once
+        assert_eq!(24, total("2d6 e6", Maximized));
+        assert_eq!(3, total("3d6 t4", Maximized));
+        assert_eq!(-3, total("3d6 t4 f1", Minimized));
+        assert_eq!(0, total("4dF", Average(FloatRounding::Ceil)));
+
+        let res = Roller::new("2d6 ie6")
+            .unwrap()
+            .roll_fixed(Maximized)
+            .unwrap();
+        assert!(res.is_truncated());
+        assert!(matches!(
+            res.as_single().unwrap().get_history()[0],
+            RollHistory::Fixed(_)
+        ));
+        let res = Roller::new("(1d8 + 2)^2")
+            .unwrap()
+            .roll_fixed(Average(FloatRounding::Ceil))
+            .unwrap();
+        assert_eq!("`{5} + 2` = **7**\n`{5} + 2` = **7**\n", res.to_string());
+    }
+}
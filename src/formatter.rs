@@ -0,0 +1,532 @@
+use crate::{
+    Critic, DiceResult, RepeatedRollResult, RollHistory, RollResult, RollResultType,
+    SingleRollResult,
+};
+
+/// How a roll result is turned into text, see [`RollResult::format()`].
+///
+/// The layout of the result is the same for every formatter, each piece of it going through one
+/// of these methods. Every method has a default giving plain text, except [`RollFormatter::text()`]
+/// which is given to every other default.
+///
+/// This crate provides [`PlainFormatter`], [`MarkdownFormatter`], used by `Display`,
+/// [`AnsiFormatter`] and [`HtmlFormatter`].
+///
+/// # Examples
+///
+/// ```
+/// use caith::{DiceResult, RollFormatter, Roller, ScriptedSource};
+///
+/// // BBCode for a forum
+/// struct BbCode;
+///
+/// impl RollFormatter for BbCode {
+///     fn text(&self, text: &str) -> String {
+///         text.to_owned()
+///     }
+///
+///     fn total(&self, total: i64) -> String {
+///         format!("[b]{}[/b]", total)
+///     }
+/// }
+///
+/// let res = Roller::new("2d6 + 1")
+///     .unwrap()
+///     .roll_with_source(&mut ScriptedSource::new(vec![3, 5]))
+///     .unwrap();
+/// assert_eq!("[5, 3] + 1 = [b]9[/b]", res.format(&BbCode));
+/// ```
+pub trait RollFormatter {
+    /// Render text which is not markup, like brackets, labels or the reason
+    fn text(&self, text: &str) -> String;
+
+    /// Render the value of one die
+    fn die(&self, die: &DiceResult) -> String {
+        self.text(&die.res.to_string())
+    }
+
+    /// Render the value of a die which doesn't count in the total, like the lowest die of `4d6 K3`.
+    /// The default puts it between parenthesis, like `(2)`.
+    fn dropped_die(&self, die: &DiceResult) -> String {
+        format!("{}{}{}", self.text("("), self.die(die), self.text(")"))
+    }
+
+    /// Render the successive values of a rerolled die, from the first one to the last one
+    fn reroll_chain(&self, chain: &[DiceResult]) -> String {
+        chain
+            .iter()
+            .map(|die| self.die(die))
+            .collect::<Vec<_>>()
+            .join(&self.text(" -> "))
+    }
+
+    /// Render an operator between two parts of the expression, like ` + `
+    fn separator(&self, separator: &str) -> String {
+        self.text(separator)
+    }
+
+    /// Render the whole history of a roll, already formatted
+    fn history(&self, history: &str) -> String {
+        history.to_owned()
+    }
+
+    /// Render the total of a roll, or the sum of a repeated roll
+    fn total(&self, total: i64) -> String {
+        self.text(&total.to_string())
+    }
+
+    /// Render the reason given after `:`
+    fn reason(&self, reason: &str) -> String {
+        format!("{}{}", self.text("Reason: "), self.text(reason))
+    }
+
+    /// Render the end of the line of one roll of a repetition
+    fn line_break(&self) -> String {
+        "\n".to_owned()
+    }
+}
+
+/// Plain text, like `[5, 3] + 1 = 9, Reason: attack`. The highest value of a die is followed by
+/// `!` and the lowest one by `?`, and the dropped dice are between parenthesis, like
+/// `[20!, 1?, (1?)]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PlainFormatter;
+
+impl RollFormatter for PlainFormatter {
+    fn text(&self, text: &str) -> String {
+        text.to_owned()
+    }
+
+    fn die(&self, die: &DiceResult) -> String {
+        match die.critic() {
+            Critic::Max => format!("{}!", die.res),
+            Critic::Min => format!("{}?", die.res),
+            Critic::No => die.res.to_string(),
+        }
+    }
+}
+
+/// Markdown as understood by Discord, like ``` `[5, 3] + 1` = **9**, Reason: `attack` ```. The
+/// highest value of a die is in bold and the lowest one underlined, the dropped dice are struck
+/// through, the code span of the history being closed around them: ``` `[`**6**`, `__1__`]` ```.
+///
+/// This is the format of `Display` for [`RollResult`] and [`SingleRollResult`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MarkdownFormatter;
+
+impl RollFormatter for MarkdownFormatter {
+    fn text(&self, text: &str) -> String {
+        text.to_owned()
+    }
+
+    fn die(&self, die: &DiceResult) -> String {
+        match die.critic() {
+            Critic::No => die.res.to_string(),
+            _ => format!("`{}`", markdown_die(die)),
+        }
+    }
+
+    fn dropped_die(&self, die: &DiceResult) -> String {
+        format!("`~~{}~~`", markdown_die(die))
+    }
+
+    fn history(&self, history: &str) -> String {
+        format!("`{}`", history)
+    }
+
+    fn total(&self, total: i64) -> String {
+        format!("**{}**", total)
+    }
+
+    fn reason(&self, reason: &str) -> String {
+        format!("Reason: `{}`", reason)
+    }
+}
+
+// Markdown isn't rendered in the code span of the history, which is closed around a marked die
+fn markdown_die(die: &DiceResult) -> String {
+    match die.critic() {
+        Critic::Max => format!("**{}**", die.res),
+        Critic::Min => format!("__{}__", die.res),
+        Critic::No => die.res.to_string(),
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// Text with the escape codes of a terminal: the total in bold, the highest value of a die in
+/// green, the lowest one in red and the dropped dice struck through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AnsiFormatter;
+
+impl RollFormatter for AnsiFormatter {
+    fn text(&self, text: &str) -> String {
+        text.to_owned()
+    }
+
+    fn die(&self, die: &DiceResult) -> String {
+        match die.critic() {
+            Critic::Max => format!("\x1b[32m{}{}", die.res, ANSI_RESET),
+            Critic::Min => format!("\x1b[31m{}{}", die.res, ANSI_RESET),
+            Critic::No => die.res.to_string(),
+        }
+    }
+
+    fn dropped_die(&self, die: &DiceResult) -> String {
+        format!("\x1b[9m{}{}", die.res, ANSI_RESET)
+    }
+
+    fn total(&self, total: i64) -> String {
+        format!("\x1b[1m{}{}", total, ANSI_RESET)
+    }
+
+    fn reason(&self, reason: &str) -> String {
+        format!("Reason: \x1b[3m{}{}", reason, ANSI_RESET)
+    }
+}
+
+/// An HTML fragment, the text being escaped. The history is a `<code>`, the total a `<strong>`,
+/// the highest and lowest values of a die are in a `<span>` of class `crit-max` or `crit-min`,
+/// and the dropped dice in a `<del>`.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{HtmlFormatter, Roller, ScriptedSource};
+///
+/// let res = Roller::new("1d20 + 5 : <attack>")
+///     .unwrap()
+///     .roll_with_source(&mut ScriptedSource::new(vec![20]))
+///     .unwrap();
+/// assert_eq!(
+///     "<code>[<span class=\"crit-max\">20</span>] + 5</code> = <strong>25</strong>, \
+///      Reason: <em>&lt;attack&gt;</em>",
+///     res.format(&HtmlFormatter)
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HtmlFormatter;
+
+impl RollFormatter for HtmlFormatter {
+    fn text(&self, text: &str) -> String {
+        text.chars().fold(String::new(), |mut s, c| {
+            match c {
+                '&' => s.push_str("&amp;"),
+                '<' => s.push_str("&lt;"),
+                '>' => s.push_str("&gt;"),
+                '"' => s.push_str("&quot;"),
+                '\'' => s.push_str("&#39;"),
+                _ => s.push(c),
+            }
+            s
+        })
+    }
+
+    fn die(&self, die: &DiceResult) -> String {
+        match die.critic() {
+            Critic::Max => format!("<span class=\"crit-max\">{}</span>", die.res),
+            Critic::Min => format!("<span class=\"crit-min\">{}</span>", die.res),
+            Critic::No => die.res.to_string(),
+        }
+    }
+
+    fn dropped_die(&self, die: &DiceResult) -> String {
+        format!("<del>{}</del>", self.die(die))
+    }
+
+    fn history(&self, history: &str) -> String {
+        format!("<code>{}</code>", history)
+    }
+
+    fn total(&self, total: i64) -> String {
+        format!("<strong>{}</strong>", total)
+    }
+
+    fn reason(&self, reason: &str) -> String {
+        format!("Reason: <em>{}</em>", self.text(reason))
+    }
+
+    fn line_break(&self) -> String {
+        "<br>\n".to_owned()
+    }
+}
+
+fn format_dice<F: RollFormatter + ?Sized>(dice: &[DiceResult], formatter: &F) -> String {
+    dice.iter()
+        .map(|die| {
+            if die.dropped {
+                formatter.dropped_die(die)
+            } else {
+                formatter.die(die)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&formatter.text(", "))
+}
+
+impl RollHistory {
+    /// Turn this step of the history into text with `formatter`
+    pub fn format<F: RollFormatter + ?Sized>(&self, formatter: &F) -> String {
+        match self {
+            RollHistory::ReRolls(v) => format!(
+                "{}{}{}",
+                formatter.text("["),
+                v.iter()
+                    .map(|chain| formatter.reroll_chain(chain))
+                    .collect::<Vec<_>>()
+                    .join(&formatter.text(", ")),
+                formatter.text("] -> ")
+            ),
+            RollHistory::Roll(v) => format!(
+                "{}{}{}",
+                formatter.text("["),
+                format_dice(v, formatter),
+                formatter.text("]")
+            ),
+            RollHistory::Fixed(v) => format!(
+                "{}{}{}",
+                formatter.text("{"),
+                format_dice(v, formatter),
+                formatter.text("}")
+            ),
+            RollHistory::Fudge(v) => {
+                let symbols = v
+                    .iter()
+                    .map(|r| {
+                        if *r <= 2 {
+                            "-"
+                        } else if *r <= 4 {
+                            "▢"
+                        } else {
+                            "+"
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                formatter.text(&format!("[{}]", symbols))
+            }
+            RollHistory::Value(v) => formatter.text(&v.to_string()),
+            RollHistory::Separator(sep) => formatter.separator(sep),
+            RollHistory::OpenParenthesis => formatter.text("("),
+            RollHistory::CloseParenthesis => formatter.text(")"),
+            RollHistory::Label(label) => formatter.text(&format!(" {}", label)),
+            RollHistory::Truncated => formatter.text("…"),
+        }
+    }
+}
+
+impl SingleRollResult {
+    /// Turn the history into text with `formatter`
+    pub fn format_history<F: RollFormatter + ?Sized>(&self, formatter: &F) -> String {
+        self.get_history()
+            .iter()
+            .map(|h| h.format(formatter))
+            .collect()
+    }
+
+    /// Turn the result into text with `formatter`: the history, the total and the subtotal of each
+    /// label.
+    pub fn format<F: RollFormatter + ?Sized>(&self, formatter: &F) -> String {
+        if self.get_history().is_empty() {
+            return formatter.history(&formatter.text(&self.get_total().to_string()));
+        }
+        let mut s = format!(
+            "{}{}{}",
+            formatter.history(&self.format_history(formatter)),
+            formatter.text(" = "),
+            formatter.total(self.get_total())
+        );
+        if !self.get_label_totals().is_empty() {
+            let labels = self
+                .get_label_totals()
+                .iter()
+                .map(|(l, t)| format!("{}: {}", l, t))
+                .collect::<Vec<_>>()
+                .join(", ");
+            s.push_str(&formatter.text(&format!(" ({})", labels)));
+        }
+        s
+    }
+}
+
+// the statistics shown by `RollResult::show_stats()`, without the sum when the total of `^+` follows
+fn stats_line(results: &RepeatedRollResult, threshold: Option<i64>, with_sum: bool) -> String {
+    let int = |v: Option<i64>| v.map_or("-".to_owned(), |v| v.to_string());
+    let float = |v: Option<f64>| {
+        v.map_or("-".to_owned(), |v| {
+            ((v * 100.0).round() / 100.0).to_string()
+        })
+    };
+    let mut line = format!(
+        "Min: {}, Max: {}, Mean: {}, Median: {}",
+        int(results.min()),
+        int(results.max()),
+        float(results.mean()),
+        float(results.median()),
+    );
+    if with_sum {
+        line.push_str(&format!(", Sum: {}", int(results.sum())));
+    }
+    if let Some(threshold) = threshold {
+        line.push_str(&format!(
+            ", Above {}: {}",
+            threshold,
+            results.count_above(threshold)
+        ));
+    }
+    line
+}
+
+impl RollResult {
+    /// Turn the result into text with `formatter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{PlainFormatter, Roller, ScriptedSource};
+    ///
+    /// let res = Roller::new("(1d6 + 1)^+2 : fire")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![3, 6]))
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     "[3] + 1\n[6!] + 1\nSum: 11, Reason: fire",
+    ///     res.format(&PlainFormatter)
+    /// );
+    /// ```
+    pub fn format<F: RollFormatter + ?Sized>(&self, formatter: &F) -> String {
+        let mut s = String::new();
+        let label = |res: &SingleRollResult| match res.get_label() {
+            Some(label) => formatter.text(&format!("{}: ", label)),
+            None => String::new(),
+        };
+        let inline_reason = self
+            .get_reason()
+            .map(|reason| format!("{}{}", formatter.text(", "), formatter.reason(reason)))
+            .unwrap_or_default();
+        let stats = |with_sum| match (self.get_stats(), self.as_repeated()) {
+            (Some(threshold), Some(results)) => format!(
+                "{}{}",
+                formatter.text(&stats_line(results, threshold, with_sum)),
+                formatter.line_break()
+            ),
+            _ => String::new(),
+        };
+        match self.get_result() {
+            RollResultType::Single(res) => {
+                s.push_str(&res.format(formatter));
+                s.push_str(&inline_reason);
+            }
+            RollResultType::Repeated(results) => match results.get_total() {
+                Some(total) => {
+                    results.iter().for_each(|res| {
+                        s.push_str(&label(res));
+                        s.push_str(&formatter.history(&res.format_history(formatter)));
+                        s.push_str(&formatter.line_break());
+                    });
+                    s.push_str(&stats(false));
+                    s.push_str(&formatter.text("Sum: "));
+                    s.push_str(&formatter.total(total));
+                    s.push_str(&inline_reason);
+                }
+                None => {
+                    results.iter().for_each(|res| {
+                        s.push_str(&label(res));
+                        s.push_str(&res.format(formatter));
+                        s.push_str(&formatter.line_break());
+                    });
+                    s.push_str(&stats(true));
+                    if let Some(reason) = self.get_reason() {
+                        s.push_str(&formatter.reason(reason));
+                    }
+                }
+            },
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::roll_dice;
+
+    #[test]
+    fn formatter_test() {
+        let res = roll_dice("2d6 r1 + 1d4 [fire] : attack", vec![1, 6, 2, 3]);
+        assert_eq!(
+            "`[`**6**`, `__1__` -> 2] -> [`**6**`, 2] + [3] fire` = **11** (fire: 3), \
+             Reason: `attack`",
+            res.format(&MarkdownFormatter)
+        );
+        assert_eq!(res.to_string(), res.format(&MarkdownFormatter));
+        assert_eq!(
+            "[6!, 1? -> 2] -> [6!, 2] + [3] fire = 11 (fire: 3), Reason: attack",
+            res.format(&PlainFormatter)
+        );
+        assert_eq!(
+            "[\x1b[32m6\x1b[0m, \x1b[31m1\x1b[0m -> 2] -> [\x1b[32m6\x1b[0m, 2] + [3] fire = \
+             \x1b[1m11\x1b[0m (fire: 3), Reason: \x1b[3mattack\x1b[0m",
+            res.format(&AnsiFormatter)
+        );
+        assert_eq!(
+            "<code>[<span class=\"crit-max\">6</span>, <span class=\"crit-min\">1</span> -&gt; 2] \
+             -&gt; [<span class=\"crit-max\">6</span>, 2] + [3] fire</code> = <strong>11</strong> \
+             (fire: 3), Reason: <em>attack</em>",
+            res.format(&HtmlFormatter)
+        );
+
+        let res = roll_dice("(1d4)^2 : [goblin A, goblin B]", vec![2, 3]);
+        assert_eq!(res.to_string(), res.format(&MarkdownFormatter));
+        assert_eq!(
+            "goblin A: <code>[2]</code> = <strong>2</strong><br>\n\
+             goblin B: <code>[3]</code> = <strong>3</strong><br>\n",
+            res.format(&HtmlFormatter)
+        );
+        let res = roll_dice("(1d4)^+2 : <b>", vec![2, 3]);
+        assert_eq!(res.to_string(), res.format(&MarkdownFormatter));
+        assert_eq!(
+            "<code>[2]</code><br>\n<code>[3]</code><br>\nSum: <strong>5</strong>, \
+             Reason: <em>&lt;b&gt;</em>",
+            res.format(&HtmlFormatter)
+        );
+        let mut res = roll_dice("(1d4)^+4", vec![2, 3, 1, 2]);
+        res.show_stats(None);
+        assert_eq!(
+            "`[2]`\n`[3]`\n`[`__1__`]`\n`[2]`\n\
+             Min: 1, Max: 3, Mean: 2, Median: 2\nSum: **8**",
+            res.to_string()
+        );
+        let mut res = roll_dice("(1d6)^3 : <b>", vec![2, 3, 3]);
+        res.show_stats(Some(2));
+        assert_eq!(
+            "<code>[2]</code> = <strong>2</strong><br>\n\
+             <code>[3]</code> = <strong>3</strong><br>\n\
+             <code>[3]</code> = <strong>3</strong><br>\n\
+             Min: 2, Max: 3, Mean: 2.67, Median: 3, Sum: 8, Above 2: 2<br>\n\
+             Reason: <em>&lt;b&gt;</em>",
+            res.format(&HtmlFormatter)
+        );
+
+        let res = roll_dice("4d6 d1 + 2d6 k1", vec![3, 1, 6, 1, 4, 4]);
+        assert_eq!(
+            "`[`**6**`, 3, `__1__`, `~~__1__~~`] + [4, `~~4~~`]` = **14**",
+            res.format(&MarkdownFormatter)
+        );
+        assert_eq!(
+            "[6!, 3, 1?, (1?)] + [4, (4)] = 14",
+            res.format(&PlainFormatter)
+        );
+        assert_eq!(
+            "[\x1b[32m6\x1b[0m, 3, \x1b[31m1\x1b[0m, \x1b[9m1\x1b[0m] + [4, \x1b[9m4\x1b[0m] = \
+             \x1b[1m14\x1b[0m",
+            res.format(&AnsiFormatter)
+        );
+        assert_eq!(
+            "<code>[<span class=\"crit-max\">6</span>, 3, <span class=\"crit-min\">1</span>, \
+             <del><span class=\"crit-min\">1</span></del>] + \
+             [4, <del>4</del>]</code> = <strong>14</strong>",
+            res.format(&HtmlFormatter)
+        );
+    }
+}
@@ -193,10 +193,10 @@ Tin Ji ({}): {}
 pub fn compute_cde(res: &RollResult, element: &str) -> Result<CdeResult> {
     let history = res
         .as_single()
-        .ok_or("Not a single roll result")?
+        .ok_or(RollError::NotSingleRoll)?
         .get_history();
     if history.len() != 1 {
-        Err("Should have only one roll".into())
+        Err(RollError::NotPlainRoll)
     } else {
         let res = history
             .iter()
@@ -208,10 +208,12 @@ pub fn compute_cde(res: &RollResult, element: &str) -> Result<CdeResult> {
                 }
             })
             .next()
-            .ok_or("RollHistory must be a Roll variant")?
+            .ok_or(RollError::NotPlainRoll)?
             .clone();
 
-        let mapping: Element = element.try_into()?;
+        let mapping: Element = element
+            .try_into()
+            .map_err(|_: &str| RollError::UnknownElement(element.to_owned()))?;
         let (mapping, elements) = match mapping {
             Element::Fire(m) => m,
             Element::Earth(m) => m,
@@ -234,7 +236,7 @@ pub fn compute_cde(res: &RollResult, element: &str) -> Result<CdeResult> {
             acc
         });
 
-        result.history = history.get(0).cloned();
+        result.history = history.first().cloned();
         result.elements = elements;
         Ok(result)
     }
@@ -243,7 +245,7 @@ pub fn compute_cde(res: &RollResult, element: &str) -> Result<CdeResult> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{tests::IteratorDiceRollSource, Critic, DiceResult, Roller};
+    use crate::{tests::IteratorDiceRollSource, DiceResult, Roller};
 
     #[test]
     fn test_cde() {
@@ -264,13 +266,7 @@ mod test {
             loksyu: (0, 1),
             tin_ji: 1,
             history: Some(RollHistory::Roll(
-                roll_mock
-                    .iter()
-                    .map(|v| DiceResult {
-                        res: *v,
-                        crit: Critic::No,
-                    })
-                    .collect(),
+                roll_mock.iter().map(|v| DiceResult::new(*v, 10)).collect(),
             )),
             elements: Default::default(), // not used in comparison
         };
@@ -293,13 +289,7 @@ mod test {
             loksyu: (0, 1),
             tin_ji: 1,
             history: Some(RollHistory::Roll(
-                roll_mock
-                    .iter()
-                    .map(|v| DiceResult {
-                        res: *v,
-                        crit: Critic::No,
-                    })
-                    .collect(),
+                roll_mock.iter().map(|v| DiceResult::new(*v, 10)).collect(),
             )),
             elements: Default::default(), // not used in comparison
         };
@@ -322,13 +312,7 @@ mod test {
             loksyu: (1, 0),
             tin_ji: 2,
             history: Some(RollHistory::Roll(
-                roll_mock
-                    .iter()
-                    .map(|v| DiceResult {
-                        res: *v,
-                        crit: Critic::No,
-                    })
-                    .collect(),
+                roll_mock.iter().map(|v| DiceResult::new(*v, 10)).collect(),
             )),
             elements: Default::default(), // not used in comparison
         };
@@ -351,13 +335,7 @@ mod test {
             loksyu: (1, 1),
             tin_ji: 3,
             history: Some(RollHistory::Roll(
-                roll_mock
-                    .iter()
-                    .map(|v| DiceResult {
-                        res: *v,
-                        crit: Critic::No,
-                    })
-                    .collect(),
+                roll_mock.iter().map(|v| DiceResult::new(*v, 10)).collect(),
             )),
             elements: Default::default(), // not used in comparison
         };
@@ -380,13 +358,7 @@ mod test {
             loksyu: (1, 2),
             tin_ji: 1,
             history: Some(RollHistory::Roll(
-                roll_mock
-                    .iter()
-                    .map(|v| DiceResult {
-                        res: *v,
-                        crit: Critic::No,
-                    })
-                    .collect(),
+                roll_mock.iter().map(|v| DiceResult::new(*v, 10)).collect(),
             )),
             elements: Default::default(), // not used in comparison
         };
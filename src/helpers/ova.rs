@@ -4,9 +4,9 @@ use crate::{error::*, RollHistory, RollResult, SingleRollResult};
 ///
 /// ex:
 /// ```
-/// use caith::*;
+/// use caith::{helpers::compute_ova, Roller};
 ///
-/// let number: i64 = -4;
+/// let number: i32 = -4;
 /// let roller = Roller::new(&format!("{}d6", number.abs())).unwrap();
 /// let res = roller.roll().unwrap();
 /// println!("{}", compute_ova(&res, number).unwrap());
@@ -14,15 +14,15 @@ use crate::{error::*, RollHistory, RollResult, SingleRollResult};
 ///
 pub fn compute_ova(res: &RollResult, number: i32) -> Result<RollResult> {
     if number == 0 {
-        return Err("Number can't be zero".into());
+        return Err(RollError::ZeroDice);
     }
 
     let res = res
         .as_single()
-        .ok_or("Not a single roll result")?
+        .ok_or(RollError::NotSingleRoll)?
         .get_history();
     if res.len() != 1 {
-        Err("Should have only one roll".into())
+        Err(RollError::NotPlainRoll)
     } else {
         let mut res = res
             .iter()
@@ -34,9 +34,9 @@ pub fn compute_ova(res: &RollResult, number: i32) -> Result<RollResult> {
                 }
             })
             .next()
-            .ok_or("RollHistory must be a Roll variant")?
+            .ok_or(RollError::NotPlainRoll)?
             .clone();
-        res.sort_unstable();
+        res.sort_unstable_by_key(|d| d.res);
         let total = if number > 0 {
             let mut last_side = 0;
             let mut current_res = 0;
@@ -0,0 +1,193 @@
+use std::fmt::Display;
+
+use crate::{Distribution, Simulation};
+
+const EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+const FULL: char = '█';
+
+/// A horizontal bar chart of the probability of each total, see [`Distribution::histogram()`] and
+/// [`Simulation::histogram()`].
+///
+/// `Display` gives one line per total, and [`Histogram::to_markdown()`] gives a Markdown table.
+///
+/// # Examples
+///
+/// ```
+/// use caith::Roller;
+///
+/// let dist = Roller::new("1d4").unwrap().distribution().unwrap();
+/// assert_eq!(
+///     "1 █████ 25.00%\n2 █████ 25.00%\n3 █████ 25.00%\n4 █████ 25.00%\n",
+///     dist.histogram().with_width(5).to_string()
+/// );
+/// assert_eq!(
+///     "1 ▊    25.00%\n2 █▌   50.00%\n3 ██▎  75.00%\n4 ███ 100.00%\n",
+///     dist.histogram().with_width(3).cumulative(true).to_string()
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pmf: Vec<(i64, f64)>,
+    width: usize,
+    cumulative: bool,
+}
+
+impl Histogram {
+    /// Create the chart of the probability of each total, in increasing order of total
+    pub fn new<T: IntoIterator<Item = (i64, f64)>>(pmf: T) -> Self {
+        Histogram {
+            pmf: pmf.into_iter().collect(),
+            width: 20,
+            cumulative: false,
+        }
+    }
+
+    /// Set the length of the longest bar, in characters. 20 by default.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Show the probability to get each total or less instead of exactly each total
+    pub fn cumulative(mut self, cumulative: bool) -> Self {
+        self.cumulative = cumulative;
+        self
+    }
+
+    // the probabilities to show, and the one of a full bar
+    fn rows(&self) -> (Vec<(i64, f64)>, f64) {
+        if self.cumulative {
+            let rows = self
+                .pmf
+                .iter()
+                .scan(0.0, |acc, &(total, p)| {
+                    *acc += p;
+                    Some((total, f64::min(*acc, 1.0)))
+                })
+                .collect();
+            (rows, 1.0)
+        } else {
+            let max = self.pmf.iter().map(|(_, p)| *p).fold(0.0, f64::max);
+            (self.pmf.clone(), max)
+        }
+    }
+
+    fn bar(&self, p: f64, max: f64) -> String {
+        let eighths = if max > 0.0 {
+            (p / max * self.width as f64 * 8.0).round() as usize
+        } else {
+            0
+        };
+        let mut bar = FULL.to_string().repeat(eighths / 8);
+        if eighths % 8 > 0 {
+            bar.push(EIGHTHS[eighths % 8 - 1]);
+        }
+        bar
+    }
+
+    /// Turn the chart into a Markdown table, with the totals and the percentages formatted like the
+    /// `Display` of [`crate::RollResult`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::Roller;
+    ///
+    /// let dist = Roller::new("1d2").unwrap().distribution().unwrap();
+    /// assert_eq!(
+    ///     "| Total | Probability | |\n|---:|---:|:---|\n| `1` | **50.00%** | ██ |\n| `2` | **50.00%** | ██ |\n",
+    ///     dist.histogram().with_width(2).to_markdown()
+    /// );
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        let (rows, max) = self.rows();
+        let mut s = format!(
+            "| Total | {} | |\n|---:|---:|:---|\n",
+            if self.cumulative {
+                "Cumulative"
+            } else {
+                "Probability"
+            }
+        );
+        for (total, p) in rows {
+            s.push_str(&format!(
+                "| `{}` | **{:.2}%** | {} |\n",
+                total,
+                p * 100.0,
+                self.bar(p, max)
+            ));
+        }
+        s
+    }
+}
+
+impl Display for Histogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (rows, max) = self.rows();
+        let total_width = rows
+            .iter()
+            .map(|(total, _)| total.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let percent_width = if rows.iter().any(|(_, p)| *p >= 0.99995) {
+            7
+        } else {
+            6
+        };
+        rows.iter().try_for_each(|&(total, p)| {
+            writeln!(
+                f,
+                "{:>tw$} {:<bw$} {:>pw$.2}%",
+                total,
+                self.bar(p, max),
+                p * 100.0,
+                tw = total_width,
+                bw = self.width,
+                pw = percent_width - 1,
+            )
+        })
+    }
+}
+
+impl Distribution {
+    /// Get the bar chart of the probability of each total
+    pub fn histogram(&self) -> Histogram {
+        Histogram::new(self.pmf())
+    }
+}
+
+impl Simulation {
+    /// Get the bar chart of the frequency of each total
+    pub fn histogram(&self) -> Histogram {
+        Histogram::new(
+            self.get_histogram()
+                .keys()
+                .map(|&total| (total, self.probability(total))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_test() {
+        let histogram = Histogram::new(vec![(-1, 0.01), (0, 0.49), (10, 0.5)]).with_width(4);
+        assert_eq!(
+            "-1 ▏     1.00%\n 0 ███▉ 49.00%\n10 ████ 50.00%\n",
+            histogram.to_string()
+        );
+        // 0.2% of 32 eighths rounds to 0
+        assert_eq!("", histogram.bar(0.001, 0.5));
+        assert_eq!("████", histogram.bar(1.0, 1.0));
+        assert_eq!("██▌", histogram.bar(0.625, 1.0));
+
+        let histogram = histogram.cumulative(true);
+        assert_eq!(
+            "| Total | Cumulative | |\n|---:|---:|:---|\n| `-1` | **1.00%** |  |\n| `0` | **50.00%** | ██ |\n| `10` | **100.00%** | ████ |\n",
+            histogram.to_markdown()
+        );
+        assert_eq!("", Histogram::new(vec![]).to_string());
+    }
+}
@@ -0,0 +1,248 @@
+use rand::Rng;
+
+use crate::{
+    error::{Result, RollError, Span},
+    DiceRollSource, RngDiceRollSource, RollResult, RollResultType, Roller, RollerLimits,
+    ThreadRngSource,
+};
+
+const DEFAULT_OPEN: &str = "[[";
+const DEFAULT_CLOSE: &str = "]]";
+const ESCAPE_CHAR: char = '\\';
+
+/// Find and roll the expressions embedded in a free text.
+///
+/// By default, expressions are enclosed in `[[` and `]]`: `"I attack [[1d20+5]] for [[2d6]]"`.
+/// An opening delimiter preceded by `\` is not an expression: the `\` is removed and the delimiter
+/// is kept as is in the text.
+///
+/// # Examples
+///
+/// ```
+/// use caith::InlineRoller;
+///
+/// let res = InlineRoller::new().roll("I attack [[1d1+5]] for [[2]]").unwrap();
+/// assert_eq!("I attack 6 for 2", res.get_text());
+/// assert_eq!(2, res.get_rolls().len());
+/// ```
+#[derive(Clone, Debug)]
+pub struct InlineRoller {
+    open: String,
+    close: String,
+    limits: RollerLimits,
+}
+
+/// Carry the text with the results substituted, and the result of each expression in order of
+/// appearance.
+#[derive(Debug, Clone)]
+pub struct InlineRollResult {
+    text: String,
+    rolls: Vec<RollResult>,
+}
+
+impl InlineRollResult {
+    /// Get the text where each expression has been replaced by its total.
+    ///
+    /// For a repeated roll, the totals are separated by a comma, unless they are summed.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the results of the expressions, in order of appearance in the text.
+    pub fn get_rolls(&self) -> &Vec<RollResult> {
+        &self.rolls
+    }
+}
+
+impl Default for InlineRoller {
+    fn default() -> Self {
+        InlineRoller {
+            open: DEFAULT_OPEN.to_owned(),
+            close: DEFAULT_CLOSE.to_owned(),
+            limits: RollerLimits::default(),
+        }
+    }
+}
+
+impl InlineRoller {
+    /// Create an `InlineRoller` using `[[` and `]]` as delimiters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an `InlineRoller` with custom delimiters, ex: `{{` and `}}`.
+    ///
+    /// Returns an error if one of the delimiters is empty.
+    pub fn with_delimiters(open: &str, close: &str) -> Result<Self> {
+        if open.is_empty() || close.is_empty() {
+            Err(RollError::EmptyDelimiter)
+        } else {
+            Ok(InlineRoller {
+                open: open.to_owned(),
+                close: close.to_owned(),
+                limits: RollerLimits::default(),
+            })
+        }
+    }
+
+    /// Set the limits to enforce for each expression, see [`RollerLimits`].
+    pub fn with_limits(mut self, limits: RollerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Roll every expression in the text with default Rng source (`rand::thread_rng()`)
+    pub fn roll(&self, text: &str) -> Result<InlineRollResult> {
+        self.roll_with_source(text, &mut ThreadRngSource::new())
+    }
+
+    /// Roll every expression in the text with provided rng source
+    pub fn roll_with<RNG: Rng>(&self, text: &str, rng: &mut RNG) -> Result<InlineRollResult> {
+        self.roll_with_source(text, &mut RngDiceRollSource { rng })
+    }
+
+    /// Roll every expression in the text with provided dice roll source
+    pub fn roll_with_source<RNG: DiceRollSource>(
+        &self,
+        text: &str,
+        rng: &mut RNG,
+    ) -> Result<InlineRollResult> {
+        let mut res = InlineRollResult {
+            text: String::with_capacity(text.len()),
+            rolls: Vec::new(),
+        };
+        // errors are reported relatively to the whole text
+        let mut rest = text;
+        while let Some(idx) = rest.find(self.open.as_str()) {
+            let (before, after) = rest.split_at(idx);
+            let after = &after[self.open.len()..];
+            if let Some(before) = before.strip_suffix(ESCAPE_CHAR) {
+                res.text.push_str(before);
+                res.text.push_str(&self.open);
+                rest = after;
+                continue;
+            }
+            res.text.push_str(before);
+
+            let open_pos = text.len() - rest.len() + idx;
+            let expr_pos = open_pos + self.open.len();
+            let end = self
+                .find_close(after)
+                .ok_or_else(|| RollError::UnclosedInlineRoll {
+                    span: Span::new(open_pos, expr_pos),
+                    delimiter: self.close.clone(),
+                })?;
+            let roll_res = Roller::new(&after[..end])?
+                .with_limits(self.limits)
+                .roll_with_source(rng)
+                .map_err(|e| e.offset(expr_pos))?;
+            res.text.push_str(&total_to_string(&roll_res));
+            res.rolls.push(roll_res);
+            rest = &after[end + self.close.len()..];
+        }
+        res.text.push_str(rest);
+        Ok(res)
+    }
+
+    // Brackets inside the expression (labels, target enumeration) must be closed before we can
+    // match the closing delimiter.
+    fn find_close(&self, s: &str) -> Option<usize> {
+        let mut depth = 0_usize;
+        for (idx, c) in s.char_indices() {
+            if depth == 0 && s[idx..].starts_with(self.close.as_str()) {
+                return Some(idx);
+            }
+            match c {
+                '[' => depth += 1,
+                ']' => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+        }
+        None
+    }
+}
+
+fn total_to_string(res: &RollResult) -> String {
+    match res.get_result() {
+        RollResultType::Single(res) => res.get_total().to_string(),
+        RollResultType::Repeated(rep) => match rep.get_total() {
+            Some(total) => total.to_string(),
+            None => rep
+                .iter()
+                .map(|res| res.get_total().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::IteratorDiceRollSource;
+
+    #[test]
+    fn inline_test() {
+        let roll_mock = vec![12, 3, 4];
+        let res = InlineRoller::new()
+            .roll_with_source(
+                "I attack [[1d20+5]] for [[2d6]]!",
+                &mut IteratorDiceRollSource {
+                    iterator: &mut roll_mock.into_iter(),
+                },
+            )
+            .unwrap();
+        assert_eq!("I attack 17 for 7!", res.get_text());
+        let totals = res
+            .get_rolls()
+            .iter()
+            .map(|r| r.as_single().unwrap().get_total())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![17, 7], totals);
+    }
+
+    #[test]
+    fn inline_escape_and_brackets_test() {
+        let roll_mock = vec![2, 3, 4, 2, 3, 4];
+        let res = InlineRoller::new()
+            .roll_with_source(
+                r"\[[not rolled]] but [[1d6[fire]]] and [[(1d6)^2]] and [[3d6 t[2,4,6]]]",
+                &mut IteratorDiceRollSource {
+                    iterator: &mut roll_mock.into_iter(),
+                },
+            )
+            .unwrap();
+        assert_eq!("[[not rolled]] but 2 and 3, 4 and 2", res.get_text());
+        assert_eq!(
+            Some(2),
+            res.get_rolls()[0]
+                .as_single()
+                .unwrap()
+                .get_label_total("fire")
+        );
+    }
+
+    #[test]
+    fn inline_delimiters_test() {
+        let roller = InlineRoller::with_delimiters("{{", "}}").unwrap();
+        let res = roller
+            .roll("no roll [[1d6]], roll {{ 4 + 2 : reason }}")
+            .unwrap();
+        assert_eq!("no roll [[1d6]], roll 6", res.get_text());
+        assert_eq!(Some(&"reason".to_owned()), res.get_rolls()[0].get_reason());
+
+        assert_eq!(
+            Err(RollError::EmptyDelimiter),
+            InlineRoller::with_delimiters("", "}}").map(|_| ())
+        );
+        let e = roller.roll("unclosed {{1d6").unwrap_err();
+        assert_eq!(Some(Span::new(9, 11)), e.span());
+        let e = roller.roll("{{1}} invalid {{1d0}}").unwrap_err();
+        assert_eq!(
+            RollError::ZeroSides {
+                span: Span::new(18, 19)
+            },
+            e
+        );
+    }
+}
@@ -56,12 +56,106 @@
 //! with the `^+` operator, the roll will be repeated and all the totals summed.
 //!
 //! Sorted repetition:
-//! with the `^#` operator, the roll will be repeated and sorted by total.
+//! with the `^#` operator, the roll will be repeated and sorted by total, and with `^#>` sorted
+//! from the highest total to the lowest.
+//!
+//! Label:
+//! [<text>] : any leaf or parenthesis can be labelled, its subtotal is given per label:
+//! `1d8[slashing] + 2d6[fire]`
 //!
 //! Reason:
-//! : : Any text after `:` will be a comment
+//! : : Any text after `:` will be a comment. For a repetition, a comma separated list with one
+//! element per repetition labels each roll instead: `(1d20 + 4)^3 : goblin A, goblin B, goblin C`.
+//! Without brackets, a list with another number of elements stays a reason. In brackets,
+//! `(1d20 + 4)^3 : [goblin A, goblin B, goblin C]`, it's always a list of labels and must have
+//! one non-empty element per repetition.
 //! ```
 //!
+//! A decimal number like `1.5` is an exact fraction: `3d6 * 1.5 + 0.5` is computed without
+//! representation error and only the final total is rounded, toward zero by default.
+//! [`Roller::with_arithmetic()`] takes an [`Arithmetic`] which can make `/` an exact division, so
+//! that `(1d6 * 1/3) * 3` gives back the die, and which chooses the [`FloatRounding`] of the final
+//! total: `Truncate`, `Floor`, `Ceil` or `Nearest`. [`SingleRollResult::get_exact()`] gives the
+//! exact total as a [`Rational`]. A decimal has at most 18 decimals.
+//!
+//! # Inline rolls
+//!
+//! Expressions can be embedded in a free text with [`InlineRoller`]:
+//! `"I attack [[1d20+5]] for [[2d6]]"` gives back the text with the totals substituted, and each
+//! [`RollResult`].
+//!
+//! # Output formats
+//!
+//! `Display` gives a result as Markdown for Discord. [`RollResult::format()`] renders it with any
+//! [`RollFormatter`], like [`PlainFormatter`], [`MarkdownFormatter`], [`AnsiFormatter`] for a
+//! terminal or [`HtmlFormatter`]. A formatter decides how to render each die, reroll chains,
+//! operators, the total and the reason.
+//!
+//! In the history, the highest value of a die is in bold and its lowest value underlined by
+//! `Display`, or followed by `!` and `?` in plain text. The dice left out of the total by a keep or
+//! drop option are struck through by `Display`, or between parenthesis in plain text, like the
+//! lowest die of `4d6 d1` in `[5, 4, 3, (2)]`. Fixed
+//! and one-sided dice are never highlighted. [`RollResult::has_critical_success()`] and
+//! [`RollResult::has_critical_failure()`] say if the only die counted, like the one of `1d20 + 5`,
+//! rolled its highest or lowest value.
+//!
+//! [`SingleRollResult::dice()`] iterates over the dice of a result, each [`DiceResult`] telling
+//! how many sides it has, if it was rolled, exploded from another die or rerolled, if it was
+//! dropped, and how it counts in the total: its value, a success, a double success, a failure or
+//! nothing.
+//!
+//! [`RepeatedRollResult`] gives the [`min()`](RepeatedRollResult::min),
+//! [`max()`](RepeatedRollResult::max), [`mean()`](RepeatedRollResult::mean),
+//! [`median()`](RepeatedRollResult::median) and [`sum()`](RepeatedRollResult::sum) of the totals
+//! of a repetition like `(4d6 d1)^6`, and [`RepeatedRollResult::count_above()`] a threshold. After
+//! [`RollResult::show_stats()`], the text of a repetition ends with a line of these statistics:
+//! `Min: 8, Max: 17, Mean: 12.5, Median: 13, Sum: 75, Above 12: 4`, the sum being left out after
+//! `^+` which already shows it.
+//!
+//! # Dice roll sources
+//!
+//! [`Roller::roll_with_source()`] takes the dice from any [`DiceRollSource`]. Besides the
+//! `rand` ones, [`SeededSource`] gives reproducible rolls, [`ScriptedSource`] gives predefined
+//! values, [`MaxSource`] and [`MinSource`] give the extreme values, and [`RecordingSource`] records
+//! the dice given by another source.
+//!
+//! [`Roller::roll_with_transcript()`] also gives a [`RollTranscript`] of the dice rolled, which can be
+//! stored as a string and evaluated again to the same result with [`Roller::replay()`], with the
+//! limits and the arithmetic of the roller.
+//!
+//! [`Roller::roll_fixed()`] doesn't roll at all: every die takes its average, rounded with a
+//! [`FloatRounding`] or alternately up and down, its maximum or its minimum, like the average
+//! damage of a monster or maximized dice on a critical hit. The history shows these dice between
+//! braces, `{4, 3}`.
+//!
+//! # Probabilities
+//!
+//! [`Roller::distribution()`] computes the exact probability of each total of an expression,
+//! without rolling, with its mean, standard deviation, minimum and maximum. Explosions and
+//! indefinite rerolls are considered up to the depth of the [`RollerLimits`] of the roller, or the
+//! one given in [`AnalysisOptions`].
+//! [`Roller::chance_to_reach()`] gives the probability to reach a difficulty, and
+//! [`Roller::chance_of_successes()`] and [`Roller::expected_successes()`] answer the same for
+//! success pools like `6d10 t7`.
+//!
+//! The other way around, [`Roller::solve_difficulty()`] finds the difficulty giving a probability
+//! of success, and [`RollTemplate::solve()`] finds the parameter of an expression like `3d6 + {}`
+//! giving a probability to reach a difficulty.
+//!
+//! Distributions and simulations can be shown as a bar chart with [`Distribution::histogram()`]
+//! and [`Simulation::histogram()`], as text or as a Markdown table.
+//!
+//! For expressions too complex to be analysed exactly, [`Roller::simulate()`] rolls the expression
+//! many times with a seed and gives the histogram, mean, variance, percentiles and the confidence
+//! interval of the mean.
+//!
+//! [`Roller::compare()`] rolls two expressions against each other, like `2d6+3` and `1d12+4`, and
+//! gives the probability of each to win and the distribution of the difference of their totals.
+//!
+//! Before rolling, [`Roller::analyze()`] gives the bounds and the average of the total, like
+//! `3–18, avg 10.5`, whether it counts successes, and how many dice it can roll at most, to reject
+//! abusive expressions.
+//!
 //! # Helpers
 //!
 //! Some helpers are provided to interpret the roll result according to specific RPG rules.
@@ -80,6 +174,60 @@
 //! `caith` can create a standard deck of 52 cards plus optional Jokers if the feature `cards`
 //! is activated. See [`cards::Deck`].
 //!
+//! # Provably fair rolls
+//!
+//! With the feature `fair`, the dice can be derived from a committed server seed and a client seed,
+//! so anyone can verify the rolls once the server seed is revealed. See the `fair` module.
+//!
+//! # Serialization
+//!
+//! With the feature `serde`, [`RollResult`] and all its parts, [`RollTranscript`] and
+//! [`RecordedRoll`] implement `Serialize` and `Deserialize`. The JSON form is stable:
+//!
+//! ```json
+//! {
+//!   "result": { "single": ROLL },
+//!   "reason": "attack"
+//! }
+//! ```
+//!
+//! `result` is `{ "repeated": { "rolls": [ROLL, ...], "total": 12 } }` for a repetition, the total
+//! being `null` unless it's summed with `^+`. `reason` is `null` when there is none.
+//!
+//! A roll `ROLL` is
+//! `{ "total": 16, "history": [STEP, ...], "exact": null, "labels": [["fire", 16]], "label": null }`.
+//! `exact` is the exact value of the total as `[numerator, denominator]`, like `[7, 2]`, when a
+//! fraction is involved, `labels` the subtotal of each label, and `label` the label of one roll of
+//! a repetition. The steps of the history are:
+//! - `{ "roll": [DIE, ...] }`: dice rolled, `{ "fixed": [DIE, ...] }` for dice not rolled
+//! - `{ "rerolls": [[DIE, ...], ...] }`: the chain of values of each rerolled die
+//! - `{ "fudge": [1, 5] }`: Fudge dice, as values from 1 to 6
+//! - `{ "value": { "int": 2 } }` or `{ "value": { "float": 1.5 } }`: a constant
+//! - `{ "separator": " + " }`: an operator, `" + "`, `" - "`, `" * "` or `" / "`
+//! - `"open_parenthesis"`, `"close_parenthesis"`, `{ "label": "fire" }` and `"truncated"`
+//!
+//! A die `DIE` is `{ "res": 4, "crit": "no", "dropped": false, "sides": 6, "origin": "rolled",
+//! "contribution": "value" }`:
+//! - `crit` is `"min"`, `"max"` or `"no"`
+//! - `dropped` says if the die was left out of the total by a keep or drop option
+//! - `origin` is `"rolled"`, `{ "exploded": { "parent": 0 } }` or `{ "rerolled": { "from": 1 } }`
+//! - `contribution` is `"value"`, `"success"`, `"double"`, `"failure"` or `"nothing"`
+//!
+//! A transcript is `{ "expression": "2d6", "limits": { "max_dice": 5000, ... }, "arithmetic": {
+//! "exact_division": false, "rounding": "truncate" }, "rolls": [{ "sides": 6, "value": 4 }, ...] }`.
+//! The limits and the arithmetic have the names of the fields of [`RollerLimits`] and
+//! [`Arithmetic`], and the missing ones take the default value. `rounding` is `"truncate"`,
+//! `"floor"`, `"ceil"` or `"nearest"`.
+//!
+//! # Limitations
+//!
+//! To protect against abusive expressions, a roller enforces [`RollerLimits`], which can be
+//! changed with [`Roller::with_limits()`]. By default, an expression can't have more than 5000 dice
+//! of 5000 sides in one dice expression, 1000 repetitions with `^`, 100 000 dice rolled in total,
+//! including explosions, rerolls and repetitions, nor more than 32 nested parenthesis: going over
+//! one of them is an error. A die explodes or is rerolled at most 100 times, the roll being then
+//! marked as truncated, see [`SingleRollResult::is_truncated()`].
+//!
 //! # Examples
 //!
 //! These examples are directly taken from DiceMaiden's Readme:
@@ -89,7 +237,10 @@
 //! `3d6 + 5` : Roll three six-sided dice and add five. Other supported static modifiers are
 //! add (+), subtract (-), multiply (*), and divide (/).
 //!
-//! `3d6 * 1.5` : Roll three six-sided dice and add 50%.
+//! `3d6 * 1.5` : Roll three six-sided dice and add 50%. The decimal is kept exact until the end of
+//! the expression, `3d6 * 1.5 + 0.5` is not truncated before adding `0.5`, and only the total is
+//! rounded. [`SingleRollResult::get_value()`] gives the exact value, which [`Value::round()`]
+//! rounds another way.
 //!
 //! `3d6 e6` : Roll three six-sided dice and explode on sixes. Some game systems call this 'open
 //! ended' dice. If the number rolled is greater than or equal to the value given for this option,
@@ -98,7 +249,7 @@
 //! The dice will only explode once with this command. Use `ie` for indefinite explosions.
 //!
 //! `3d6 ie6` or `3d6!` : Roll three six-sided dice and explode on sixes indefinitely within reason.
-//! We will cap explosions at 100 rolls to prevent abuse.
+//! We will cap explosions at 100 rolls to prevent abuse (see [`RollerLimits`]).
 //!
 //! `3d10 d1` : Roll three ten-sided dice and drop one die. The lowest value will be dropped first.  
 //!
@@ -109,7 +260,8 @@
 //! Use `ir` for indefinite rerolls.
 //!
 //! `4d6 ir2` : Roll four six-sided dice and reroll any that are equal to or less than two (and do
-//! the same to those dice). This is capped at 100 rerolls per die to prevent abuse.
+//! the same to those dice). This is capped at 100 rerolls per die to prevent abuse (see
+//! [`RollerLimits`]).
 //!
 //! `6d10 t7` : Roll six ten-sided dice and any that are seven or higher are counted as a success.
 //! The dice in the roll are not added together for a total. Any die that meets or exceeds the
@@ -130,6 +282,9 @@
 //!
 //! `4d6 : Hello World!`: Roll four six-sided dice and add comment to the roll.
 //!
+//! `1d8[slashing] + 2d6[fire] + 3[slashing]`: Roll one eight-sided die and two six-sided dice,
+//! and give the total of slashing damages and fire damages separately, in addition to the total.
+//!
 //! These commands can be combined. For example:
 //!
 //! `10d6 e6 K8 +4` : Roll ten six-sided dice , explode on sixes and keep eight of the highest rolls
@@ -143,18 +298,49 @@ use pest::{
 
 pub mod helpers;
 
+mod analysis;
+mod comparison;
+mod distribution;
 mod error;
+mod fixed;
+mod formatter;
+mod histogram;
+mod inline;
+mod limits;
 mod parser;
+mod rational;
 mod rollresult;
+mod simulation;
+mod solver;
+mod source;
+mod success;
+mod transcript;
 
 #[cfg(feature = "cards")]
 #[cfg_attr(docsrs, doc(cfg(feature = "cards")))]
 pub mod cards;
 
+#[cfg(feature = "fair")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fair")))]
+pub mod fair;
+
+pub use analysis::*;
+pub use comparison::*;
+pub use distribution::*;
 pub use error::*;
+pub use fixed::*;
+pub use formatter::*;
+pub use histogram::*;
+pub use inline::*;
+pub use limits::*;
+pub use rational::*;
 pub use rollresult::*;
+pub use simulation::*;
+pub use solver::*;
+pub use source::*;
+pub use transcript::*;
 
-use parser::{DiceRollSource, RollParser, Rule};
+use parser::{RollContext, RollParser, Rule};
 use rand::Rng;
 
 const REASON_CHAR: char = ':';
@@ -169,22 +355,10 @@ const REASON_CHAR: char = ':';
 /// and [Forum topic](https://users.rust-lang.org/t/how-to-deal-with-external-type-which-is-send-and-sync/47530)
 ///
 #[derive(Clone, Debug)]
-pub struct Roller(String);
-
-struct RngDiceRollSource<'a, T>
-where
-    T: Rng,
-{
-    rng: &'a mut T,
-}
-
-impl<T> DiceRollSource for RngDiceRollSource<'_, T>
-where
-    T: Rng,
-{
-    fn roll_single_die(&mut self, sides: u64) -> u64 {
-        self.rng.gen_range(1..1 + sides)
-    }
+pub struct Roller {
+    input: String,
+    limits: RollerLimits,
+    arithmetic: Arithmetic,
 }
 
 impl Roller {
@@ -196,12 +370,38 @@ impl Roller {
     /// can fail) and saved, see `Roller` documentation above.
     ///
     pub fn new(input: &str) -> Result<Self> {
-        Ok(Roller(input.to_owned()))
+        Ok(Roller {
+            input: input.to_owned(),
+            limits: RollerLimits::default(),
+            arithmetic: Arithmetic::default(),
+        })
+    }
+
+    /// Set the limits to enforce during evaluation, see [`RollerLimits`] for the default ones.
+    pub fn with_limits(mut self, limits: RollerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Get the limits enforced during evaluation
+    pub fn get_limits(&self) -> &RollerLimits {
+        &self.limits
+    }
+
+    /// Set how the expression is computed, see [`Arithmetic`] for the default.
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    /// Get how the expression is computed
+    pub fn get_arithmetic(&self) -> Arithmetic {
+        self.arithmetic
     }
 
     /// Evaluate and roll the dices with default Rng source (`rand::thread_rng()`)
     pub fn roll(&self) -> Result<RollResult> {
-        self.roll_with(&mut rand::thread_rng())
+        self.roll_with_source(&mut ThreadRngSource::new())
     }
 
     /// Evaluate and roll the dices with provided rng source
@@ -211,68 +411,124 @@ impl Roller {
 
     /// Evaluate and roll the dice with provided dice roll source
     pub fn roll_with_source<RNG: DiceRollSource>(&self, rng: &mut RNG) -> Result<RollResult> {
-        let mut pairs = RollParser::parse(Rule::command, &self.0)?;
+        self.roll_parsed(self.parse()?, rng)
+    }
+
+    // Evaluate an expression already parsed, to parse it once when rolling it many times
+    fn roll_parsed<RNG: DiceRollSource>(
+        &self,
+        pairs: Pairs<'_, Rule>,
+        rng: &mut RNG,
+    ) -> Result<RollResult> {
+        self.roll_in_context(
+            pairs,
+            &mut RollContext::new(rng, &self.limits).with_arithmetic(self.arithmetic),
+        )
+    }
+
+    // Evaluate an expression with the given evaluation state
+    fn roll_in_context<RNG: DiceRollSource>(
+        &self,
+        mut pairs: Pairs<'_, Rule>,
+        ctx: &mut RollContext<RNG>,
+    ) -> Result<RollResult> {
         let expr_type = pairs.next().unwrap();
-        let mut roll_res = match expr_type.as_rule() {
-            Rule::expr => {
-                RollResult::new_single(parser::compute(expr_type.into_inner(), rng, false)?)
-            }
-            Rule::repeated_expr => Roller::process_repeated_expr(expr_type, rng)?,
+        let reason = pairs
+            .next()
+            .filter(|reason| reason.as_rule() == Rule::reason)
+            .map(|reason| {
+                let text = &reason.as_str()[1..];
+                let trimmed = text.trim();
+                let start = reason.as_span().start() + 1 + text.len() - text.trim_start().len();
+                (trimmed.to_owned(), Span::new(start, start + trimmed.len()))
+            });
+        let (mut roll_res, reason) = match expr_type.as_rule() {
+            Rule::expr => (
+                RollResult::new_single(parser::compute(expr_type.into_inner(), ctx, false)?),
+                reason.map(|(reason, _)| reason),
+            ),
+            Rule::repeated_expr => self.process_repeated_expr(expr_type, reason, ctx)?,
             _ => unreachable!(),
         };
 
-        if let Some(reason) = pairs.next() {
-            if reason.as_rule() == Rule::reason {
-                roll_res.add_reason(reason.as_str()[1..].trim().to_owned());
-            }
+        if let Some(reason) = reason {
+            roll_res.add_reason(reason);
         }
         Ok(roll_res)
     }
 
+    // If the reason is a list of labels, see `repetition_labels()`, each element labels one roll
+    // and the reason is consumed.
     fn process_repeated_expr<RNG: DiceRollSource>(
+        &self,
         expr_type: Pair<Rule>,
-        rng: &mut RNG,
-    ) -> Result<RollResult> {
+        reason: Option<(String, Span)>,
+        ctx: &mut RollContext<RNG>,
+    ) -> Result<(RollResult, Option<String>)> {
+        let expr_span = expr_type.as_span().into();
         let mut pairs = expr_type.into_inner();
         let expr = pairs.next().unwrap();
         let maybe_option = pairs.next().unwrap();
         let (number, sum_all, sort) = match maybe_option.as_rule() {
-            Rule::number => (maybe_option.as_str().parse::<i64>().unwrap(), false, false),
-            Rule::add => (
-                pairs.next().unwrap().as_str().parse::<i64>().unwrap(),
-                true,
-                false,
-            ),
-            Rule::sort => (
-                pairs.next().unwrap().as_str().parse::<i64>().unwrap(),
-                false,
-                true,
-            ),
+            Rule::number => (maybe_option, false, None),
+            Rule::add => (pairs.next().unwrap(), true, None),
+            Rule::sort => {
+                let descending = maybe_option.into_inner().next().is_some();
+                (pairs.next().unwrap(), false, Some(descending))
+            }
             _ => unreachable!(),
         };
+        let span = number.as_span().into();
+        let number = number
+            .as_str()
+            .parse::<i64>()
+            .map_err(|_| RollError::Overflow { span })?;
         if number <= 0 {
-            Err("Can't repeat 0 times or negatively".into())
+            Err(RollError::InvalidRepetition { span })
+        } else if number as u64 > self.limits.max_repetitions {
+            Err(RollError::TooManyRepetitions {
+                span,
+                requested: number as u64,
+                max: self.limits.max_repetitions,
+            })
         } else {
             let results: Result<Vec<SingleRollResult>> =
                 (0..number).try_fold(Vec::new(), |mut res, _| {
-                    let c = parser::compute(expr.clone().into_inner(), rng, false)?;
+                    let c = parser::compute(expr.clone().into_inner(), ctx, false)?;
                     res.push(c);
                     Ok(res)
                 });
             let mut results = results?;
-            if sort {
-                results.sort_unstable_by(|a, b| a.get_total().partial_cmp(&b.get_total()).unwrap());
+            let reason = match reason {
+                Some((reason, reason_span)) => {
+                    match repetition_labels(&reason, reason_span, number as u64)? {
+                        Some(labels) => {
+                            results
+                                .iter_mut()
+                                .zip(labels)
+                                .for_each(|(res, label)| res.set_label(label));
+                            None
+                        }
+                        None => Some(reason),
+                    }
+                }
+                None => None,
+            };
+            match sort {
+                Some(true) => results.sort_by_key(|r| std::cmp::Reverse(r.get_total())),
+                Some(false) => results.sort_by_key(|r| r.get_total()),
+                None => (),
             }
             let total = if sum_all {
-                Some(
-                    results
-                        .iter()
-                        .fold(0, |acc, current| acc + current.get_total()),
-                )
+                let total = results
+                    .iter()
+                    .try_fold(0_i64, |acc, current| acc.checked_add(current.get_total()))
+                    .ok_or(RollError::Overflow { span: expr_span })?;
+                Some(total)
             } else {
                 None
             };
-            Ok(RollResult::new_repeated(results, total))
+            Ok((RollResult::new_repeated(results, total), reason))
         }
     }
 
@@ -286,24 +542,66 @@ impl Roller {
     /// let r = Roller::new("1d6 + 1d4 + 1d10 + 1d20").unwrap();
     /// assert_eq!(vec!["1d6", "1d4", "1d10", "1d20"], r.dices().expect("Error on parse").collect::<Vec<_>>());
     /// ```
-    pub fn dices(&self) -> Result<Dices> {
-        let pairs = RollParser::parse(Rule::command, &self.0)?
-            .next()
-            .unwrap()
-            .into_inner();
+    pub fn dices(&self) -> Result<Dices<'_>> {
+        let pairs = self.parse()?.next().unwrap().into_inner();
         Ok(Dices { pairs })
     }
 
     /// Give back the query string
     pub fn as_str(&self) -> &str {
-        &self.0
+        &self.input
     }
 
     /// Removes the reason from the Roller
     pub fn trim_reason(&mut self) {
-        if let Some(idx) = self.0.find(REASON_CHAR) {
-            self.0 = self.0[..idx].to_owned()
+        if let Some(idx) = self.input.find(REASON_CHAR) {
+            self.input = self.input[..idx].to_owned()
+        }
+    }
+
+    // Nesting is measured on the parsed expression, so parenthesis in labels and reason don't count
+    fn parse(&self) -> Result<Pairs<'_, Rule>> {
+        let pairs = RollParser::parse(Rule::command, &self.input)
+            .map_err(|e| RollError::from_pest(&self.input, e))?;
+        let mut stack: Vec<_> = pairs.clone().map(|pair| (pair, 0)).collect();
+        while let Some((pair, mut depth)) = stack.pop() {
+            if let Rule::block_expr | Rule::repeated_expr = pair.as_rule() {
+                depth += 1;
+                if depth > self.limits.max_depth {
+                    let start = pair.as_span().start();
+                    return Err(RollError::TooDeep {
+                        span: Span::new(start, start + 1),
+                        max: self.limits.max_depth,
+                    });
+                }
+            }
+            stack.extend(pair.into_inner().map(|pair| (pair, depth)));
         }
+        Ok(pairs)
+    }
+}
+
+// The labels of the rolls of a repetition, given as reason. A list in brackets is always a list of
+// labels, with one non-empty label per roll. Without brackets, a comma separated list labels the
+// rolls only if it has one non-empty element per roll, otherwise it's a reason.
+fn repetition_labels(reason: &str, span: Span, repetitions: u64) -> Result<Option<Vec<String>>> {
+    let bracketed = reason.strip_prefix('[').and_then(|r| r.strip_suffix(']'));
+    let labels: Vec<String> = bracketed
+        .unwrap_or(reason)
+        .split(',')
+        .map(|label| label.trim().to_owned())
+        .collect();
+    let valid = labels.len() as u64 == repetitions && labels.iter().all(|l| !l.is_empty());
+    if bracketed.is_some() && !valid {
+        Err(RollError::InvalidRepetitionLabels {
+            span,
+            labels: labels.iter().filter(|l| !l.is_empty()).count(),
+            repetitions,
+        })
+    } else if valid && (bracketed.is_some() || repetitions > 1) {
+        Ok(Some(labels))
+    } else {
+        Ok(None)
     }
 }
 
@@ -352,6 +650,14 @@ mod tests {
         }
     }
 
+    // Roll `input` with `dice` as the values of the dice rolled, in order
+    pub(crate) fn roll_dice(input: &str, dice: Vec<u64>) -> RollResult {
+        Roller::new(input)
+            .unwrap()
+            .roll_with_source(&mut ScriptedSource::new(dice))
+            .unwrap()
+    }
+
     #[test]
     fn get_repeat_test() {
         let r = Roller::new("(2d6 + 6) ^ 8 : test").unwrap();
@@ -406,6 +712,22 @@ mod tests {
         eprintln!("{}", roll_res);
     }
 
+    #[test]
+    fn get_repeat_sort_descending_test() {
+        let roll = |input: &str| {
+            roll_dice(input, vec![3, 6, 1, 6, 4])
+                .as_repeated()
+                .unwrap()
+                .iter()
+                .map(|r| r.get_total())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![6, 6, 4, 3, 1], roll("(1d6)^#>5"));
+        assert_eq!(vec![1, 3, 4, 6, 6], roll("(1d6)^#5"));
+        assert_eq!(vec![3, 6, 1, 6, 4], roll("(1d6)^5"));
+        assert!(Roller::new("(1d6)^>5").unwrap().roll().is_err());
+    }
+
     #[test]
     fn get_repeat_sum_test() {
         let r = Roller::new("(2d6 + 6) ^+ 2 : test").unwrap();
@@ -463,7 +785,7 @@ mod tests {
         if let RollResultType::Single(res) = res {
             assert_eq!(20, res.get_total());
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -532,6 +854,87 @@ mod tests {
         }
     }
 
+    #[test]
+    fn float_precision_test() {
+        let single = |input: &str, mock| roll_dice(input, mock).as_single().unwrap().clone();
+        let value = |input: &str, mock| single(input, mock).get_value();
+        // 3 * 1.5 isn't truncated to 4 before being multiplied
+        assert_eq!(Value::Float(9.0), value("(2d6 * 1.5) * 2", vec![1, 2]));
+        assert_eq!(Value::Float(12.0), value("1d6 / (1d4 * 0.5)", vec![6, 1]));
+        assert_eq!(Value::Int(3), value("2d6 / 1", vec![1, 2]));
+        let res = value("1d6 * -0.5", vec![5]);
+        assert_eq!(Some(-2), res.round(FloatRounding::Truncate));
+        assert_eq!(Some(-3), res.round(FloatRounding::Floor));
+        assert_eq!(Some(-2), res.round(FloatRounding::Ceil));
+        assert_eq!(Some(-3), res.round(FloatRounding::Nearest));
+        let exact = |input: &str, mock| single(input, mock).get_exact();
+        // no representation error, and more than two decimals
+        // 0.7 / 0.1 would be 6.999999999999999 with floats
+        assert_eq!(Rational::from(7), exact("1d6 * 0.7 / 0.1", vec![1]));
+        assert_eq!(Rational::new(1, 8), Some(exact("1d4 * 0.125", vec![1])));
+        let too_many = RollError::TooManyDecimals {
+            span: Span::new(6, 27),
+            max: 18,
+        };
+        let roller = Roller::new("1d6 * 0.1234567890123456789").unwrap();
+        assert_eq!(Err(too_many.clone()), roller.roll().map(|_| ()));
+        assert_eq!(Err(too_many.clone()), roller.analyze().map(|_| ()));
+        assert_eq!(Err(too_many), roller.distribution().map(|_| ()));
+        assert_eq!(
+            Rational::new(123_456_789_012_345_678, 1_000_000_000_000_000_000),
+            Some(exact("1 * 0.123456789012345678", vec![]))
+        );
+    }
+
+    #[test]
+    fn exact_arithmetic_test() {
+        let roll = |input: &str, mock: Vec<u64>, arithmetic| {
+            let res = Roller::new(input)
+                .unwrap()
+                .with_arithmetic(arithmetic)
+                .roll_with_source(&mut ScriptedSource::new(mock))
+                .unwrap();
+            let res = res.as_single().unwrap();
+            (res.get_total(), res.get_exact())
+        };
+        let exact = |rounding| Arithmetic {
+            exact_division: true,
+            rounding,
+        };
+        let r = |n, d| Rational::new(n, d).unwrap();
+        assert_eq!(
+            (3, r(3, 1)),
+            roll("(1d6 * 1/3) * 3", vec![5], Arithmetic::default())
+        );
+        for &die in [1, 2, 3, 4, 5, 6].iter() {
+            let res = roll("(1d6 * 1/3) * 3", vec![die], exact(FloatRounding::Truncate));
+            assert_eq!((die as i64, r(die as i64, 1)), res);
+        }
+        assert_eq!(
+            (-3, r(-5, 2)),
+            roll("1d6 / -2", vec![5], exact(FloatRounding::Floor))
+        );
+        assert_eq!(
+            (-2, r(-5, 2)),
+            roll("1d6 / -2", vec![5], exact(FloatRounding::Ceil))
+        );
+        assert_eq!(
+            (3, r(5, 2)),
+            roll("1d6 / 2", vec![5], exact(FloatRounding::Nearest))
+        );
+        // the rounding policy also applies to decimals, and only to the final total
+        let nearest = Arithmetic {
+            rounding: FloatRounding::Nearest,
+            ..Arithmetic::default()
+        };
+        assert_eq!((5, r(9, 2)), roll("1d6 * 1.5", vec![3], nearest));
+        assert_eq!((2, r(2, 1)), roll("1d6 / 2", vec![5], nearest));
+        assert_eq!(
+            (4, r(15, 4)),
+            roll("(1d6 * 1.5) / 2", vec![5], exact(FloatRounding::Nearest))
+        );
+    }
+
     #[test]
     fn float_signed_add_test() {
         let r = Roller::new("20 + +1.5").unwrap();
@@ -593,7 +996,7 @@ mod tests {
         if let RollResultType::Single(res) = res {
             assert_eq!(res.get_total(), 12);
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -612,7 +1015,7 @@ mod tests {
             // on the 7, 8, 9, and 10. So four total.
             assert_eq!(res.get_total(), 4);
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -631,7 +1034,7 @@ mod tests {
             // success each for the 9 and 10. So a toal of six.
             assert_eq!(res.get_total(), 6);
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -652,7 +1055,7 @@ mod tests {
             // So eight total.
             assert_eq!(res.get_total(), 8);
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -672,7 +1075,7 @@ mod tests {
             // So six total.
             assert_eq!(res.get_total(), 6);
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -690,7 +1093,7 @@ mod tests {
             // We rolled one of every number. That's half of them being even
             assert_eq!(res.get_total(), 3);
         } else {
-            assert!(false);
+            unreachable!();
         }
 
         let mock = vec![1, 2, 2, 4, 6, 3];
@@ -705,7 +1108,7 @@ mod tests {
             // We rolled one of every number. That's half of them being even
             assert_eq!(res.get_total(), 4);
         } else {
-            assert!(false);
+            unreachable!();
         }
 
         let mock = vec![1, 3, 3, 4, 6, 3];
@@ -720,7 +1123,7 @@ mod tests {
             // We rolled one of every number. That's half of them being even
             assert_eq!(res.get_total(), 2);
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -737,7 +1140,7 @@ mod tests {
     #[test]
     fn caith_minimal() {
         // This should deterministically roll a 1
-        let roller = Roller::new(&"1d1").unwrap();
+        let roller = Roller::new("1d1").unwrap();
 
         let result = roller.roll().unwrap();
         let numeric = result.as_single().unwrap();
@@ -752,7 +1155,7 @@ mod tests {
     #[test]
     fn caith_reroll() {
         // This should deterministically roll a 1, then reroll 1
-        let roller = Roller::new(&"1d1 r1").unwrap();
+        let roller = Roller::new("1d1 r1").unwrap();
 
         let result = roller.roll().unwrap();
         let numeric = result.as_single().unwrap();
@@ -768,7 +1171,7 @@ mod tests {
     #[test]
     fn caith_no_reroll() {
         // This should deterministically roll a 1, then not reroll anything since 1 > 0
-        let roller = Roller::new(&"1d1 r0").unwrap();
+        let roller = Roller::new("1d1 r0").unwrap();
 
         let result = roller.roll().unwrap();
         let numeric = result.as_single().unwrap();
@@ -779,4 +1182,273 @@ mod tests {
         assert_eq!(as_string, "[1] = 1");
         assert_eq!(history, "[1]");
     }
+
+    #[test]
+    fn caith_no_indefinite_reroll() {
+        // no die is rerolled, but they still count
+        let result = roll_dice("3d4 ir1", vec![2, 3, 4]);
+        assert_eq!(9, result.as_single().unwrap().get_total());
+    }
+
+    #[test]
+    fn repeat_label_test() {
+        let r = Roller::new("(1d20 + 4)^3 : [goblin A, goblin B, goblin C]").unwrap();
+        let roll_mock = vec![10, 2, 15];
+        let res = r
+            .roll_with_source(&mut IteratorDiceRollSource {
+                iterator: &mut roll_mock.into_iter(),
+            })
+            .unwrap();
+        assert!(res.get_reason().is_none());
+        let labels = res
+            .as_repeated()
+            .unwrap()
+            .iter()
+            .map(|r| r.get_label().unwrap().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["goblin A", "goblin B", "goblin C"], labels);
+        assert_eq!(
+            "goblin A: `[10] + 4` = **14**\ngoblin B: `[2] + 4` = **6**\ngoblin C: `[15] + 4` = **19**\n",
+            res.to_string()
+        );
+
+        // without brackets too
+        let r = Roller::new("(1d20 + 4)^2 : goblin A, goblin B").unwrap();
+        let res = r.roll().unwrap();
+        assert!(res.get_reason().is_none());
+        let labels = res
+            .as_repeated()
+            .unwrap()
+            .iter()
+            .map(|r| r.get_label().unwrap().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["goblin A", "goblin B"], labels);
+
+        // in brackets, one label per roll is required
+        assert_eq!(
+            Err(RollError::InvalidRepetitionLabels {
+                span: Span::new(11, 31),
+                labels: 2,
+                repetitions: 3
+            }),
+            Roller::new("(1d20)^3 : [goblin A, goblin B]")
+                .unwrap()
+                .roll()
+                .map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidRepetitionLabels {
+                span: Span::new(11, 16),
+                labels: 1,
+                repetitions: 2
+            }),
+            Roller::new("(1d20)^2 : [a, ]").unwrap().roll().map(|_| ())
+        );
+
+        // without brackets, another number of elements stays a reason
+        for reason in &["attack, defend", "attack, then, defend, flee", "attack"] {
+            let r = Roller::new(&format!("(1d20)^3 : {}", reason)).unwrap();
+            let res = r.roll().unwrap();
+            assert_eq!(Some(&reason.to_string()), res.get_reason());
+            assert!(res
+                .as_repeated()
+                .unwrap()
+                .iter()
+                .all(|r| r.get_label().is_none()));
+        }
+    }
+
+    #[test]
+    fn error_span_test() {
+        let e = Roller::new("1d6 + 6000d6").unwrap().roll().unwrap_err();
+        assert_eq!(
+            RollError::TooManyDice {
+                span: Span::new(6, 10),
+                requested: 6000,
+                max: 5000
+            },
+            e
+        );
+
+        let e = Roller::new("2d6000").unwrap().roll().unwrap_err();
+        assert_eq!(Some(Span::new(2, 6)), e.span());
+        assert!(matches!(e, RollError::TooManySides { .. }));
+
+        let e = Roller::new("3d0 + 1").unwrap().roll().unwrap_err();
+        assert_eq!(
+            RollError::ZeroSides {
+                span: Span::new(2, 3)
+            },
+            e
+        );
+
+        let e = Roller::new("1d6 / (2 - 2)").unwrap().roll().unwrap_err();
+        assert_eq!(
+            RollError::DivideByZero {
+                span: Span::new(6, 13)
+            },
+            e
+        );
+
+        let e = Roller::new("2d6 K3").unwrap().roll().unwrap_err();
+        assert_eq!(
+            RollError::NotEnoughDice {
+                span: Span::new(0, 6),
+                requested: 3,
+                available: 2
+            },
+            e
+        );
+        assert_eq!(Some("keep or drop at most 2 dice".to_owned()), e.hint());
+
+        let e = Roller::new("(1d6)^0").unwrap().roll().unwrap_err();
+        assert_eq!(
+            RollError::InvalidRepetition {
+                span: Span::new(6, 7)
+            },
+            e
+        );
+    }
+
+    #[test]
+    fn syntax_error_test() {
+        let e = Roller::new("4d6 kh3").unwrap().roll().unwrap_err();
+        assert_eq!(Some(Span::new(5, 6)), e.span());
+        assert_eq!(Some("did you mean `K3`?".to_owned()), e.hint());
+
+        let e = Roller::new("2x6 + 1").unwrap().roll().unwrap_err();
+        assert_eq!(Some(Span::new(1, 2)), e.span());
+        assert_eq!(Some("did you mean `2d6`?".to_owned()), e.hint());
+        match &e {
+            RollError::Syntax {
+                found, expected, ..
+            } => {
+                assert_eq!(&Some("x".to_owned()), found);
+                assert!(expected.contains(&"an operator"));
+            }
+            _ => unreachable!(),
+        }
+
+        let e = Roller::new("(1d6 + 2").unwrap().roll().unwrap_err();
+        assert_eq!(Some(Span::new(8, 8)), e.span());
+        assert_eq!(Some("missing `)`".to_owned()), e.hint());
+
+        let e = Roller::new("1d").unwrap().roll().unwrap_err();
+        assert_eq!(
+            Some("missing number of sides, did you mean `1d6`?".to_owned()),
+            e.hint()
+        );
+        assert_eq!(
+            "Unexpected end of expression, expected `F` or a number \
+            (missing number of sides, did you mean `1d6`?)",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn dice_metadata_test() {
+        let dice = |input: &str, mock: Vec<u64>| {
+            roll_dice(input, mock)
+                .as_single()
+                .unwrap()
+                .dice()
+                .map(|d| (d.res, d.sides, d.origin, d.contribution, d.dropped))
+                .collect::<Vec<_>>()
+        };
+
+        use Contribution::*;
+        use DieOrigin::*;
+        // the parents are counted across the whole expression
+        assert_eq!(
+            vec![
+                (4, 4, Rolled, Value, false),
+                (6, 6, Rolled, Value, false),
+                (5, 6, Rolled, Nothing, true),
+                (2, 6, Rolled, Nothing, true),
+                (6, 6, Exploded { parent: 1 }, Value, false),
+                (3, 6, Exploded { parent: 4 }, Nothing, true),
+            ],
+            dice("1d4 + 3d6 ie6 K2", vec![4, 6, 2, 5, 6, 3])
+        );
+        assert_eq!(
+            vec![
+                (6, 6, Rerolled { from: 2 }, Value, false),
+                (5, 6, Rolled, Value, false),
+                (3, 6, Rerolled { from: 1 }, Value, false),
+            ],
+            dice("3d6 ir2", vec![1, 2, 5, 1, 3, 6])
+        );
+        assert_eq!(
+            vec![
+                (10, 10, Rolled, Double, false),
+                (7, 10, Rolled, Success, false),
+                (4, 10, Rolled, Nothing, false),
+                (1, 10, Rolled, Failure, false),
+            ],
+            dice("4d10 t7 tt10 f1", vec![1, 7, 10, 4])
+        );
+
+        // results are equal only if their dice are the same in every way, not only by value
+        assert_ne!(DiceResult::new(6, 6), DiceResult::new(6, 8));
+        assert_ne!(
+            roll_dice("1d6", vec![6]).as_single(),
+            roll_dice("1d8", vec![6]).as_single()
+        );
+        assert!(DiceResult::new(2, 6) < DiceResult::new(3, 4));
+    }
+
+    #[test]
+    fn critical_test() {
+        let res = roll_dice("1d20 + 5", vec![20]);
+        assert!(res.has_critical_success());
+        assert!(!res.has_critical_failure());
+        assert_eq!("`[`**20**`] + 5` = **25**", res.to_string());
+        assert!(roll_dice("1d20", vec![1]).has_critical_failure());
+        assert!(roll_dice("2d20 k1", vec![1, 20]).has_critical_failure());
+        // two dice are counted
+        assert!(!roll_dice("2d20", vec![20, 20]).has_critical_success());
+        assert!(!roll_dice("1d20 e20", vec![20, 3]).has_critical_success());
+        // a one-sided die is not highlighted, nor critical
+        let res = roll_dice("1d1", vec![1]);
+        assert!(!res.has_critical_success());
+        assert!(!res.has_critical_failure());
+        assert_eq!("`[1]` = **1**", res.to_string());
+        assert!(roll_dice("(1d20)^2", vec![3, 20]).has_critical_success());
+        // a fixed die is not rolled
+        let res = Roller::new("1d20")
+            .unwrap()
+            .roll_fixed(FixedMode::Minimized)
+            .unwrap();
+        assert!(!res.has_critical_failure());
+        assert_eq!("`{1}` = **1**", res.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_test() {
+        let res = roll_dice("(2d6 r1 + 1.5) * 2 [fire] : attack", vec![1, 4, 3]);
+        let json = serde_json::to_string(&res).unwrap();
+        assert_eq!(res, serde_json::from_str(&json).unwrap());
+        let res = Roller::new("1d5 + 1")
+            .unwrap()
+            .roll_fixed(FixedMode::Average(FloatRounding::Floor))
+            .unwrap();
+        assert_eq!(
+            r#"{"total":4,"history":[{"fixed":[{"res":3,"crit":"no","dropped":false,"sides":5,"origin":"rolled","contribution":"value"}]},{"separator":" + "},{"value":{"int":1}}],"exact":null,"labels":[],"label":null}"#,
+            serde_json::to_string(res.as_single().unwrap()).unwrap()
+        );
+
+        let (mut res, transcript) = Roller::new("(1d4)^+2")
+            .unwrap()
+            .roll_with_transcript()
+            .unwrap();
+        // the statistics are not serialized, and not compared
+        res.show_stats(Some(2));
+        let json = serde_json::to_string(&res).unwrap();
+        assert_eq!(res, serde_json::from_str(&json).unwrap());
+        let json = serde_json::to_string(&transcript).unwrap();
+        assert_eq!(transcript, serde_json::from_str(&json).unwrap());
+
+        assert!(serde_json::from_str::<RollHistory>(r#"{"separator":" % "}"#).is_err());
+    }
 }
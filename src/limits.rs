@@ -0,0 +1,52 @@
+/// Limits enforced while evaluating an expression, to protect against abusive expressions.
+///
+/// Going over `max_dice`, `max_sides`, `max_repetitions`, `max_total_dice` or `max_depth` is an
+/// error. Going over `max_explosions` or `max_rerolls` stops the explosions or rerolls, and the
+/// roll is marked as truncated, see [`crate::SingleRollResult::is_truncated()`].
+///
+/// # Examples
+///
+/// ```
+/// use caith::{Roller, RollerLimits};
+///
+/// let limits = RollerLimits {
+///     max_dice: 100,
+///     ..Default::default()
+/// };
+/// let roller = Roller::new("200d6").unwrap().with_limits(limits);
+/// assert!(roller.roll().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RollerLimits {
+    /// Maximum number of dice in one dice expression, like `5000` in `5000d6`
+    pub max_dice: u64,
+    /// Maximum number of sides of a dice
+    pub max_sides: u64,
+    /// Maximum number of successive explosions for one dice expression
+    pub max_explosions: u64,
+    /// Maximum number of rerolls of one die
+    pub max_rerolls: u64,
+    /// Maximum number of repetitions with `^`
+    pub max_repetitions: u64,
+    /// Maximum number of dice rolled during the whole evaluation, including explosions, rerolls and
+    /// repetitions
+    pub max_total_dice: u64,
+    /// Maximum nesting of parenthesis
+    pub max_depth: usize,
+}
+
+impl Default for RollerLimits {
+    fn default() -> Self {
+        RollerLimits {
+            max_dice: 5000,
+            max_sides: 5000,
+            max_explosions: 100,
+            max_rerolls: 100,
+            max_repetitions: 1000,
+            max_total_dice: 100_000,
+            max_depth: 32,
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use std::sync::{Arc, Once, RwLock};
+use std::{
+    str::FromStr,
+    sync::{Arc, Once, RwLock},
+};
 
 use pest::{
     iterators::{Pair, Pairs},
@@ -6,19 +9,68 @@ use pest::{
 };
 use pest_derive::Parser;
 
-use crate::{error::Result, DiceResult, SingleRollResult};
-
-pub trait DiceRollSource {
-    fn roll_single_die(&mut self, sides: u64) -> u64;
-}
+use crate::{
+    error::{Result, RollError, Span},
+    Arithmetic, DiceResult, DiceRollSource, DieOrigin, Rational, RollerLimits, SingleRollResult,
+};
 
 #[derive(Parser)]
 #[grammar = "caith.pest"]
 pub(crate) struct RollParser;
 
-// arbitrary limit to avoid OOM
-const MAX_DICE_SIDES: u64 = 5000;
-const MAX_NUMBER_OF_DICE: u64 = 5000;
+// State of an evaluation: where the dice come from, and the limits to respect
+pub(crate) struct RollContext<'a, RNG: DiceRollSource> {
+    rng: &'a mut RNG,
+    limits: &'a RollerLimits,
+    dice_rolled: u64,
+    // dice expression being evaluated
+    dice_span: Span,
+    // the source gives fixed values instead of rolling
+    fixed: bool,
+    arithmetic: Arithmetic,
+}
+
+impl<'a, RNG: DiceRollSource> RollContext<'a, RNG> {
+    pub(crate) fn new(rng: &'a mut RNG, limits: &'a RollerLimits) -> Self {
+        RollContext {
+            rng,
+            limits,
+            dice_rolled: 0,
+            dice_span: Span::default(),
+            fixed: false,
+            arithmetic: Arithmetic::default(),
+        }
+    }
+
+    pub(crate) fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    // mark the dice as fixed in the history, see `Roller::roll_fixed()`
+    pub(crate) fn fixed(mut self) -> Self {
+        self.fixed = true;
+        self
+    }
+
+    fn roll_dice(&mut self, num: u64, sides: u64) -> Result<Vec<DiceResult>> {
+        self.dice_rolled = self.dice_rolled.saturating_add(num);
+        if self.dice_rolled > self.limits.max_total_dice {
+            return Err(RollError::TooManyDiceRolled {
+                span: self.dice_span,
+                max: self.limits.max_total_dice,
+            });
+        }
+        let mut values = Vec::with_capacity(num as usize);
+        self.rng
+            .roll_many(num, sides, &mut values)
+            .map_err(|e| e.with_source_span(self.dice_span))?;
+        Ok(values
+            .into_iter()
+            .map(|res| DiceResult::new(res, sides))
+            .collect())
+    }
+}
 
 // number represent nb dice to keep/drop
 #[derive(Clone, PartialEq)]
@@ -40,28 +92,30 @@ struct OptionResult {
 
 // Struct to have a singleton of PrecClimber without using once_cell
 #[derive(Clone)]
-struct Climber {
+pub(crate) struct Climber {
     inner: Arc<RwLock<PrattParser<Rule>>>,
 }
 
 impl Climber {
-    fn climb<'i, P, F, G, T>(&self, pairs: P, primary: F, infix: G) -> T
+    pub(crate) fn climb<'i, P, F, G, H, T>(&self, pairs: P, primary: F, infix: G, postfix: H) -> T
     where
         P: Iterator<Item = Pair<'i, Rule>>,
         F: FnMut(Pair<'i, Rule>) -> T,
         G: FnMut(T, Pair<'i, Rule>, T) -> T + 'i,
+        H: FnMut(T, Pair<'i, Rule>) -> T + 'i,
     {
         self.inner
             .read()
             .unwrap()
             .map_primary(primary)
             .map_infix(infix)
+            .map_postfix(postfix)
             .parse(pairs)
     }
 }
 
-fn get_climber() -> Climber {
-    static mut PREC_CLIMBER: *const Climber = 0 as *const Climber;
+pub(crate) fn get_climber() -> Climber {
+    static mut PREC_CLIMBER: *const Climber = std::ptr::null();
     static ONCE: Once = Once::new();
 
     unsafe {
@@ -73,12 +127,13 @@ fn get_climber() -> Climber {
                 inner: Arc::new(RwLock::new(
                     PrattParser::new()
                         .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::sub, Assoc::Left))
-                        .op(Op::infix(Rule::mul, Assoc::Left) | Op::infix(Rule::div, Assoc::Left)),
+                        .op(Op::infix(Rule::mul, Assoc::Left) | Op::infix(Rule::div, Assoc::Left))
+                        .op(Op::postfix(Rule::label)),
                 )),
             };
 
             // Put it in the heap so it can outlive this call
-            PREC_CLIMBER = std::mem::transmute(Box::new(singleton));
+            PREC_CLIMBER = std::mem::transmute::<Box<Climber>, *const Climber>(Box::new(singleton));
         });
 
         // Now we give out a copy of the data that is safe to use concurrently.
@@ -86,29 +141,40 @@ fn get_climber() -> Climber {
     }
 }
 
+// link each die rolled by an explosion to the die which exploded
+fn link_explosions(mut res: Vec<DiceResult>, parents: Vec<usize>) -> Vec<DiceResult> {
+    res.iter_mut()
+        .zip(parents)
+        .for_each(|(die, parent)| die.origin = DieOrigin::Exploded { parent });
+    res
+}
+
 fn compute_explode<RNG: DiceRollSource>(
     rolls: &mut SingleRollResult,
     sides: u64,
     res: Vec<DiceResult>,
     option: Pair<Rule>,
     prev_modifier: &TotalModifier,
-    rng: &mut RNG,
-) -> (TotalModifier, Vec<DiceResult>) {
-    let value = extract_option_value(option).unwrap_or(sides);
+    ctx: &mut RollContext<RNG>,
+) -> Result<(TotalModifier, Vec<DiceResult>)> {
+    let value = extract_option_value(option)?.unwrap_or(sides);
     let nb = res.iter().filter(|x| x.res >= value).count() as u64;
     if prev_modifier != &TotalModifier::None(Rule::explode)
         && prev_modifier != &TotalModifier::None(Rule::i_explode)
     {
         rolls.add_history(res.clone(), false);
     }
-    let res = if nb > 0 {
-        let res = roll_dice(nb, sides, rng);
+    let res = if nb > 0 && ctx.limits.max_explosions == 0 {
+        rolls.add_truncated();
+        res
+    } else if nb > 0 {
+        let res = link_explosions(ctx.roll_dice(nb, sides)?, rolls.exploding_dice(value));
         rolls.add_history(res.clone(), false);
         res
     } else {
         res
     };
-    (TotalModifier::None(Rule::explode), res)
+    Ok((TotalModifier::None(Rule::explode), res))
 }
 
 fn compute_i_explode<RNG: DiceRollSource>(
@@ -117,9 +183,9 @@ fn compute_i_explode<RNG: DiceRollSource>(
     res: Vec<DiceResult>,
     option: Pair<Rule>,
     prev_modifier: &TotalModifier,
-    rng: &mut RNG,
-) -> (TotalModifier, Vec<DiceResult>) {
-    let value = extract_option_value(option).unwrap_or(sides);
+    ctx: &mut RollContext<RNG>,
+) -> Result<(TotalModifier, Vec<DiceResult>)> {
+    let value = extract_option_value(option)?.unwrap_or(sides);
     if prev_modifier != &TotalModifier::None(Rule::explode)
         && prev_modifier != &TotalModifier::None(Rule::i_explode)
     {
@@ -127,12 +193,18 @@ fn compute_i_explode<RNG: DiceRollSource>(
     }
     let mut nb = res.into_iter().filter(|x| x.res >= value).count() as u64;
     let mut res = Vec::new();
+    let mut explosions = 0;
     while nb > 0 {
-        res = roll_dice(nb, sides, rng);
+        if explosions == ctx.limits.max_explosions {
+            rolls.add_truncated();
+            break;
+        }
+        explosions += 1;
+        res = link_explosions(ctx.roll_dice(nb, sides)?, rolls.exploding_dice(value));
         nb = res.iter().filter(|x| x.res >= value).count() as u64;
         rolls.add_history(res.clone(), false);
     }
-    (TotalModifier::None(Rule::i_explode), res)
+    Ok((TotalModifier::None(Rule::i_explode), res))
 }
 
 fn compute_reroll<RNG: DiceRollSource>(
@@ -140,34 +212,40 @@ fn compute_reroll<RNG: DiceRollSource>(
     sides: u64,
     res: Vec<DiceResult>,
     option: Pair<Rule>,
-    rng: &mut RNG,
-) -> (TotalModifier, Vec<DiceResult>) {
-    let value = extract_option_value(option).unwrap();
+    ctx: &mut RollContext<RNG>,
+) -> Result<(TotalModifier, Vec<DiceResult>)> {
+    let value = extract_option_value(option)?.unwrap();
     let mut has_rerolled = false;
+    let mut is_truncated = false;
     let mut rerolls: Vec<Vec<DiceResult>> = vec![];
-    let res_new: Vec<DiceResult> = res
-        .iter()
-        .map(|x| {
-            let mut inner = vec![*x];
-            let result = if x.res <= value {
-                has_rerolled = true;
-                let rerolled = roll_dice(1, sides, rng)[0];
-                inner.push(rerolled);
-                rerolled
-            } else {
-                *x
-            };
-            rerolls.push(inner);
-            result
-        })
-        .collect();
+    let mut res_new: Vec<DiceResult> = Vec::with_capacity(res.len());
+    for x in res.iter() {
+        let mut inner = vec![*x];
+        let result = if x.res <= value && ctx.limits.max_rerolls == 0 {
+            is_truncated = true;
+            *x
+        } else if x.res <= value {
+            has_rerolled = true;
+            let mut rerolled = ctx.roll_dice(1, sides)?[0];
+            rerolled.origin = DieOrigin::Rerolled { from: x.res };
+            inner.push(rerolled);
+            rerolled
+        } else {
+            *x
+        };
+        rerolls.push(inner);
+        res_new.push(result);
+    }
 
     if has_rerolled {
         rolls.add_rerolled_history(rerolls);
     }
     rolls.add_history(res_new.clone(), false);
+    if is_truncated {
+        rolls.add_truncated();
+    }
 
-    (TotalModifier::None(Rule::reroll), res_new)
+    Ok((TotalModifier::None(Rule::reroll), res_new))
 }
 
 fn compute_i_reroll<RNG: DiceRollSource>(
@@ -175,26 +253,32 @@ fn compute_i_reroll<RNG: DiceRollSource>(
     sides: u64,
     res: Vec<DiceResult>,
     option: Pair<Rule>,
-    rng: &mut RNG,
-) -> (TotalModifier, Vec<DiceResult>) {
-    let value = extract_option_value(option).unwrap();
-    let mut has_rerolled = false;
-    let res: Vec<DiceResult> = res
-        .into_iter()
-        .map(|x| {
-            let mut x = x;
-            while x.res <= value {
-                has_rerolled = true;
-                x = roll_dice(1, sides, rng)[0]
+    ctx: &mut RollContext<RNG>,
+) -> Result<(TotalModifier, Vec<DiceResult>)> {
+    let value = extract_option_value(option)?.unwrap();
+    let mut is_truncated = false;
+    let mut res_new: Vec<DiceResult> = Vec::with_capacity(res.len());
+    for mut x in res.into_iter() {
+        let from = x.res;
+        let mut rerolls = 0;
+        while x.res <= value {
+            if rerolls == ctx.limits.max_rerolls {
+                is_truncated = true;
+                break;
             }
-            x
-        })
-        .collect();
+            rerolls += 1;
+            x = ctx.roll_dice(1, sides)?[0];
+            x.origin = DieOrigin::Rerolled { from };
+        }
+        res_new.push(x);
+    }
 
-    if has_rerolled {
-        rolls.add_history(res.clone(), false);
+    // the dice are counted even if none was rerolled
+    rolls.add_history(res_new.clone(), false);
+    if is_truncated {
+        rolls.add_truncated();
     }
-    (TotalModifier::None(Rule::i_reroll), res)
+    Ok((TotalModifier::None(Rule::i_reroll), res_new))
 }
 
 fn compute_option<RNG: DiceRollSource>(
@@ -202,37 +286,37 @@ fn compute_option<RNG: DiceRollSource>(
     sides: u64,
     res: Vec<DiceResult>,
     option: Pair<Rule>,
-    rng: &mut RNG,
+    ctx: &mut RollContext<RNG>,
     prev_modifier: &TotalModifier,
 ) -> Result<OptionResult> {
     let (modifier, mut res) = match &option.as_rule() {
-        Rule::explode => compute_explode(rolls, sides, res, option, prev_modifier, rng),
-        Rule::i_explode => compute_i_explode(rolls, sides, res, option, prev_modifier, rng),
-        Rule::reroll => compute_reroll(rolls, sides, res, option, rng),
-        Rule::i_reroll => compute_i_reroll(rolls, sides, res, option, rng),
+        Rule::explode => compute_explode(rolls, sides, res, option, prev_modifier, ctx)?,
+        Rule::i_explode => compute_i_explode(rolls, sides, res, option, prev_modifier, ctx)?,
+        Rule::reroll => compute_reroll(rolls, sides, res, option, ctx)?,
+        Rule::i_reroll => compute_i_reroll(rolls, sides, res, option, ctx)?,
         Rule::keep_hi => {
-            let value = extract_option_value(option).unwrap();
+            let value = extract_option_value(option)?.unwrap();
             if rolls.get_history().is_empty() {
                 rolls.add_history(res.clone(), false);
             }
             (TotalModifier::KeepHi(value as usize), res)
         }
         Rule::keep_lo => {
-            let value = extract_option_value(option).unwrap();
+            let value = extract_option_value(option)?.unwrap();
             if rolls.get_history().is_empty() {
                 rolls.add_history(res.clone(), false);
             }
             (TotalModifier::KeepLo(value as usize), res)
         }
         Rule::drop_hi => {
-            let value = extract_option_value(option).unwrap();
+            let value = extract_option_value(option)?.unwrap();
             if rolls.get_history().is_empty() {
                 rolls.add_history(res.clone(), false);
             }
             (TotalModifier::DropHi(value as usize), res)
         }
         Rule::drop_lo => {
-            let value = extract_option_value(option).unwrap();
+            let value = extract_option_value(option)?.unwrap();
             if rolls.get_history().is_empty() {
                 rolls.add_history(res.clone(), false);
             }
@@ -242,29 +326,25 @@ fn compute_option<RNG: DiceRollSource>(
             let value_or_enum = option.into_inner().next().unwrap();
             match value_or_enum.as_rule() {
                 Rule::number => (
-                    TotalModifier::TargetFailureDouble(
-                        value_or_enum.as_str().parse::<u64>().unwrap(),
-                        0,
-                        0,
-                    ),
+                    TotalModifier::TargetFailureDouble(parse_number(&value_or_enum)?, 0, 0),
                     res,
                 ),
                 Rule::target_enum => {
                     let numbers_list = value_or_enum.into_inner();
-                    let numbers_list: Vec<_> = numbers_list
-                        .map(|p| p.as_str().parse::<u64>().unwrap())
-                        .collect();
+                    let numbers_list = numbers_list
+                        .map(|p| parse_number(&p))
+                        .collect::<Result<Vec<_>>>()?;
                     (TotalModifier::TargetEnum(numbers_list), res)
                 }
                 _ => unreachable!(),
             }
         }
         Rule::double_target => {
-            let value = extract_option_value(option).unwrap();
+            let value = extract_option_value(option)?.unwrap();
             (TotalModifier::TargetFailureDouble(0, 0, value), res)
         }
         Rule::failure => {
-            let value = extract_option_value(option).unwrap();
+            let value = extract_option_value(option)?.unwrap();
             (TotalModifier::TargetFailureDouble(0, value, 0), res)
         }
         _ => unreachable!("{:#?}", option),
@@ -290,7 +370,7 @@ fn compute_option<RNG: DiceRollSource>(
         | TotalModifier::TargetEnum(_)
         | TotalModifier::Fudge => 0,
     };
-    res.sort_unstable();
+    res.sort_unstable_by_key(|d| d.res);
     let res = match modifier {
         TotalModifier::KeepHi(_) => res[res.len() - n..].to_vec(),
         TotalModifier::KeepLo(_) => res[..n].to_vec(),
@@ -304,22 +384,25 @@ fn compute_option<RNG: DiceRollSource>(
     Ok(OptionResult { res, modifier })
 }
 
-fn compute_roll<RNG: DiceRollSource>(
-    mut dice: Pairs<Rule>,
-    rng: &mut RNG,
+pub(crate) fn compute_roll<RNG: DiceRollSource>(
+    dice: Pair<Rule>,
+    ctx: &mut RollContext<RNG>,
 ) -> Result<SingleRollResult> {
+    let span = dice.as_span().into();
+    ctx.dice_span = span;
+    let mut dice = dice.into_inner();
     let mut rolls = SingleRollResult::new();
     let number_of_dice = dice.next().unwrap();
     let number_of_dice = match number_of_dice.as_rule() {
         Rule::nb_dice => {
             dice.next(); // skip `d` token
-            let n = number_of_dice.as_str().parse::<u64>().unwrap();
-            if n > MAX_NUMBER_OF_DICE {
-                return Err(format!(
-                    "Exceed maximum allowed number of dices ({})",
-                    MAX_NUMBER_OF_DICE
-                )
-                .into());
+            let n = parse_number(&number_of_dice)?;
+            if n > ctx.limits.max_dice {
+                return Err(RollError::TooManyDice {
+                    span: number_of_dice.as_span().into(),
+                    requested: n,
+                    max: ctx.limits.max_dice,
+                });
             }
             n
         }
@@ -329,25 +412,31 @@ fn compute_roll<RNG: DiceRollSource>(
 
     let pair = dice.next().unwrap();
     let (sides, is_fudge) = match pair.as_rule() {
-        Rule::number => (pair.as_str().parse::<u64>().unwrap(), false),
+        Rule::number => (parse_number(&pair)?, false),
         Rule::fudge => (6, true),
         _ => unreachable!("{:?}", pair),
     };
 
     if sides == 0 {
-        return Err("Dice can't have 0 sides".into());
-    } else if sides > MAX_DICE_SIDES {
-        return Err(format!("Dice can't have more than {}", MAX_DICE_SIDES).into());
+        return Err(RollError::ZeroSides {
+            span: pair.as_span().into(),
+        });
+    } else if sides > ctx.limits.max_sides {
+        return Err(RollError::TooManySides {
+            span: pair.as_span().into(),
+            requested: sides,
+            max: ctx.limits.max_sides,
+        });
     }
 
-    let mut res = roll_dice(number_of_dice, sides, rng);
+    let mut res = ctx.roll_dice(number_of_dice, sides)?;
     let mut modifier = TotalModifier::None(Rule::expr);
     let mut next_option = dice.next();
     if !is_fudge {
         if next_option.is_some() {
             while next_option.is_some() {
                 let option = next_option.unwrap();
-                let opt_res = compute_option(&mut rolls, sides, res, option, rng, &modifier)?;
+                let opt_res = compute_option(&mut rolls, sides, res, option, ctx, &modifier)?;
                 res = opt_res.res;
                 modifier = match opt_res.modifier {
                     TotalModifier::TargetFailureDouble(t, f, d) => match modifier {
@@ -376,14 +465,21 @@ fn compute_roll<RNG: DiceRollSource>(
         } else {
             rolls.add_history(res, is_fudge);
         }
-        rolls.compute_total(modifier)?;
+        rolls.compute_total(modifier, span)?;
     } else {
         rolls.add_history(res, is_fudge);
-        rolls.compute_total(if is_fudge {
-            TotalModifier::Fudge
-        } else {
-            TotalModifier::None(Rule::expr)
-        })?;
+        rolls.compute_total(
+            if is_fudge {
+                TotalModifier::Fudge
+            } else {
+                TotalModifier::None(Rule::expr)
+            },
+            span,
+        )?;
+    }
+
+    if ctx.fixed {
+        rolls.mark_fixed();
     }
 
     Ok(rolls)
@@ -392,54 +488,87 @@ fn compute_roll<RNG: DiceRollSource>(
 // compute a whole roll expression
 pub(crate) fn compute<RNG: DiceRollSource>(
     expr: Pairs<Rule>,
-    rng: &mut RNG,
+    ctx: &mut RollContext<RNG>,
     is_block: bool,
 ) -> Result<SingleRollResult> {
+    let arithmetic = ctx.arithmetic;
+    // the span of each operand is kept to report where a division by zero happens
     let res = get_climber().climb(
         expr,
-        |pair: Pair<Rule>| match pair.as_rule() {
-            Rule::integer => Ok(SingleRollResult::with_total(
-                pair.as_str().replace(' ', "").parse::<i64>().unwrap(),
-            )),
-            Rule::float => Ok(SingleRollResult::with_float(
-                pair.as_str().replace(' ', "").parse::<f64>().unwrap(),
-            )),
-            Rule::block_expr => {
-                let expr = pair.into_inner().next().unwrap().into_inner();
-                compute(expr, rng, true)
-            }
-            Rule::dice => compute_roll(pair.into_inner(), rng),
-            _ => unreachable!("{:#?}", pair),
+        |pair: Pair<Rule>| {
+            let span = Span::from(pair.as_span());
+            let res = match pair.as_rule() {
+                Rule::integer => pair
+                    .as_str()
+                    .replace(' ', "")
+                    .parse::<i64>()
+                    .map(SingleRollResult::with_total)
+                    .map_err(|_| RollError::Overflow { span }),
+                Rule::float => parse_decimal(&pair).map(|value| {
+                    // the grammar only accepts a valid float
+                    let literal = pair.as_str().replace(' ', "").parse::<f64>().unwrap();
+                    SingleRollResult::with_fraction(value, literal)
+                }),
+                Rule::block_expr => {
+                    let expr = pair.into_inner().next().unwrap().into_inner();
+                    compute(expr, ctx, true)
+                }
+                Rule::dice => compute_roll(pair, ctx),
+                _ => unreachable!("{:#?}", pair),
+            };
+            res.map(|res| (res, span))
         },
-        |lhs: Result<SingleRollResult>, op: Pair<Rule>, rhs: Result<SingleRollResult>| match (
-            lhs, rhs,
-        ) {
-            (Ok(lhs), Ok(rhs)) => match op.as_rule() {
-                Rule::add => Ok(lhs + rhs),
-                Rule::sub => Ok(lhs - rhs),
-                Rule::mul => Ok(lhs * rhs),
+        |lhs: Result<(SingleRollResult, Span)>,
+         op: Pair<Rule>,
+         rhs: Result<(SingleRollResult, Span)>| {
+            let (lhs, lspan) = lhs?;
+            let (rhs, rspan) = rhs?;
+            let span = lspan.join(rspan);
+            // the labelled subtotals of a product or a quotient must be scaled by a plain value
+            let labelled = |res: &SingleRollResult| !res.get_label_totals().is_empty();
+            match op.as_rule() {
+                Rule::mul if labelled(&lhs) && labelled(&rhs) => {
+                    return Err(RollError::InvalidLabelOperation { span });
+                }
+                Rule::div if labelled(&rhs) => {
+                    return Err(RollError::InvalidLabelOperation { span: rspan });
+                }
+                _ => (),
+            }
+            let res = match op.as_rule() {
+                Rule::add => lhs.checked_add(rhs),
+                Rule::sub => lhs.checked_sub(rhs),
+                Rule::mul => lhs.checked_mul(rhs),
                 Rule::div => {
                     if rhs.is_zero() {
-                        Err("Can't divide by zero".into())
+                        return Err(RollError::DivideByZero { span: rspan });
+                    } else if arithmetic.exact_division {
+                        lhs.checked_div_exact(rhs)
                     } else {
-                        Ok(lhs / rhs)
+                        lhs.checked_div(rhs)
                     }
                 }
                 _ => unreachable!(),
-            },
-            (Err(e), _) => Err(e),
-            (_, Err(e)) => Err(e),
+            };
+            res.map(|res| (res, span))
+                .ok_or(RollError::Overflow { span })
+        },
+        |lhs: Result<(SingleRollResult, Span)>, label: Pair<Rule>| {
+            let (mut lhs, span) = lhs?;
+            let label_span = label.as_span().into();
+            let text = label.into_inner().next().unwrap().as_str().trim();
+            lhs.add_label(text.to_owned());
+            Ok((lhs, span.join(label_span)))
         },
     );
-    match res {
-        Ok(mut single_roll_res) => {
-            if is_block {
-                single_roll_res.add_parenthesis();
-            }
-            Ok(single_roll_res)
+    res.map(|(mut single_roll_res, _)| {
+        if is_block {
+            single_roll_res.add_parenthesis();
+        } else {
+            single_roll_res.round_total(arithmetic.rounding);
         }
-        e @ Err(_) => e,
-    }
+        single_roll_res
+    })
 }
 
 pub(crate) fn find_first_dice(expr: &mut Pairs<Rule>) -> Option<String> {
@@ -456,19 +585,215 @@ pub(crate) fn find_first_dice(expr: &mut Pairs<Rule>) -> Option<String> {
     None
 }
 
-pub(crate) fn roll_dice<RNG: DiceRollSource>(
-    num: u64,
-    sides: u64,
-    rng: &mut RNG,
-) -> Vec<DiceResult> {
-    (0..num)
-        .map(|_| DiceResult::new(rng.roll_single_die(sides), sides))
-        .collect()
-}
-
-fn extract_option_value(option: Pair<Rule>) -> Option<u64> {
+pub(crate) fn extract_option_value(option: Pair<Rule>) -> Result<Option<u64>> {
     option
         .into_inner()
         .next()
-        .map(|p| p.as_str().parse::<u64>().unwrap())
+        .map(|p| parse_number(&p))
+        .transpose()
+}
+
+// a denominator of 10^18 is the biggest power of 10 fitting in an `i64`
+const MAX_DECIMALS: usize = 18;
+
+// a decimal number is an exact fraction, which may not fit
+pub(crate) fn parse_decimal(pair: &Pair<Rule>) -> Result<Rational> {
+    let span = pair.as_span().into();
+    let literal = pair.as_str().replace(' ', "");
+    Rational::from_decimal(&literal).ok_or_else(|| {
+        let decimals = literal
+            .split_once('.')
+            .map_or(0, |(_, fraction)| fraction.len());
+        if decimals > MAX_DECIMALS {
+            RollError::TooManyDecimals {
+                span,
+                max: MAX_DECIMALS,
+            }
+        } else {
+            RollError::Overflow { span }
+        }
+    })
+}
+
+// the grammar only accepts digits, so parsing can only fail on overflow
+pub(crate) fn parse_number<T: FromStr>(pair: &Pair<Rule>) -> Result<T> {
+    pair.as_str()
+        .trim()
+        .parse::<T>()
+        .map_err(|_| RollError::Overflow {
+            span: pair.as_span().into(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::{RollError, Span},
+        tests::roll_dice,
+        Roller, RollerLimits, ScriptedSource,
+    };
+
+    #[test]
+    fn label_test() {
+        let res = roll_dice("1d8[slashing] + 2d6 [ fire ] + 3[slashing]", vec![5, 3, 4]);
+        let res = res.as_single().unwrap();
+        assert_eq!(15, res.get_total());
+        assert_eq!(Some(8), res.get_label_total("slashing"));
+        assert_eq!(Some(7), res.get_label_total("fire"));
+        assert_eq!(None, res.get_label_total("cold"));
+        assert_eq!(
+            &[("slashing".to_owned(), 8), ("fire".to_owned(), 7)],
+            res.get_label_totals()
+        );
+        assert_eq!(
+            "[5] slashing + [4, 3] fire + 3 slashing = 15 (slashing: 8, fire: 7)",
+            res.to_string(false)
+        );
+
+        // a label can't be blank
+        let e = Roller::new("1d6[ ]").unwrap().roll().unwrap_err();
+        assert_eq!(Some(Span::new(5, 6)), e.span());
+        assert_eq!("Unexpected `]`, expected a label", e.to_string());
+        for input in &["1d6[]", "1d6[\u{a0}\t]"] {
+            let e = Roller::new(input).unwrap().roll().unwrap_err();
+            assert!(matches!(e, RollError::Syntax { .. }));
+        }
+    }
+
+    #[test]
+    fn label_block_test() {
+        let res = roll_dice("(1d8 + 2)[slashing] - 1d4[fire] * 2 : attack", vec![5, 3]);
+        assert_eq!(Some(&"attack".to_owned()), res.get_reason());
+        let res = res.as_single().unwrap();
+        assert_eq!(1, res.get_total());
+        assert_eq!(Some(7), res.get_label_total("slashing"));
+        assert_eq!(Some(-6), res.get_label_total("fire"));
+        assert_eq!(
+            "([5] + 2) slashing - [3] fire * 2 = 1 (slashing: 7, fire: -6)",
+            res.to_string(false)
+        );
+    }
+
+    #[test]
+    fn label_product_test() {
+        let roll = |input: &str| Roller::new(input).unwrap().roll();
+        let res = roll("2 * 1d4[fire] / 2").unwrap();
+        let res = res.as_single().unwrap();
+        assert_eq!(Some(res.get_total()), res.get_label_total("fire"));
+
+        assert_eq!(
+            Err(RollError::InvalidLabelOperation {
+                span: Span::new(0, 15)
+            }),
+            roll("1d6[a] * 1d6[b]").map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidLabelOperation {
+                span: Span::new(5, 14)
+            }),
+            roll("10 / 1d4[fire]").map(|_| ())
+        );
+        assert!(roll("(1d6 * 1d4)[fire] / 2").is_ok());
+    }
+
+    #[test]
+    fn label_target_enum_test() {
+        let res = roll_dice("3d6 t[2,4,6][even]", vec![2, 3, 4]);
+        let res = res.as_single().unwrap();
+        assert_eq!(2, res.get_total());
+        assert_eq!(Some(2), res.get_label_total("even"));
+    }
+
+    #[test]
+    fn limits_truncation_test() {
+        let res = Roller::new("4d1 ie1").unwrap().roll().unwrap();
+        assert!(res.is_truncated());
+        let res = res.as_single().unwrap();
+        // 4 original dice and 100 explosions of 4 dice
+        assert_eq!(404, res.get_total());
+        assert!(res.to_string(false).contains('…'));
+
+        let limits = RollerLimits {
+            max_rerolls: 3,
+            ..Default::default()
+        };
+        let res = Roller::new("2d6 ir1")
+            .unwrap()
+            .with_limits(limits)
+            .roll_with_source(&mut ScriptedSource::new(vec![1, 4, 1, 1, 1]))
+            .unwrap();
+        assert!(res.is_truncated());
+        assert_eq!(5, res.as_single().unwrap().get_total());
+
+        let res = Roller::new("4d6 ie6").unwrap().roll().unwrap();
+        assert!(!res.is_truncated());
+    }
+
+    #[test]
+    fn limits_error_test() {
+        let limits = RollerLimits {
+            max_dice: 10,
+            max_sides: 20,
+            max_repetitions: 5,
+            max_total_dice: 30,
+            max_depth: 2,
+            ..Default::default()
+        };
+        let roll = |input: &str| Roller::new(input).unwrap().with_limits(limits).roll();
+
+        assert!(matches!(
+            roll("11d6"),
+            Err(RollError::TooManyDice { max: 10, .. })
+        ));
+        assert!(matches!(
+            roll("1d100"),
+            Err(RollError::TooManySides { max: 20, .. })
+        ));
+        assert_eq!(
+            Err(RollError::TooManyRepetitions {
+                span: Span::new(6, 7),
+                requested: 6,
+                max: 5
+            }),
+            roll("(1d6)^6").map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::TooManyDiceRolled {
+                span: Span::new(8, 12),
+                max: 30
+            }),
+            roll("(10d6 + 10d6)^2").map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::TooDeep {
+                span: Span::new(14, 15),
+                max: 2
+            }),
+            roll("((1d6) + (1 + (1d4)))").map(|_| ())
+        );
+        assert!(roll("((1d6)) : (((reason)))").is_ok());
+        assert!(roll("1d6 [fire (((( big ]").is_ok());
+        assert!(roll("(10d6 + 10d6)^1").is_ok());
+    }
+
+    #[test]
+    fn overflow_test() {
+        let roll = |input: &str| Roller::new(input).unwrap().roll().map(|_| ());
+        let overflow = |start, end| {
+            Err(RollError::Overflow {
+                span: Span::new(start, end),
+            })
+        };
+
+        assert_eq!(overflow(0, 20), roll("99999999999999999999"));
+        assert_eq!(overflow(0, 23), roll("9223372036854775807 + 1"));
+        assert!(roll("(0 - 9223372036854775807 - 1)").is_ok());
+        assert_eq!(overflow(0, 34), roll("(0 - 9223372036854775807 - 1) / -1"));
+        assert_eq!(overflow(0, 25), roll("4611686018427387904 * 2.0"));
+        assert_eq!(overflow(0, 20), roll("99999999999999999999d6"));
+        assert_eq!(overflow(5, 25), roll("2d6 k99999999999999999999"));
+        assert_eq!(overflow(7, 27), roll("(1d6)^+99999999999999999999"));
+        assert_eq!(overflow(0, 30), roll("(4611686018427387904 + 1d1)^+2"));
+        assert!(roll("9223372036854775807 + 0").is_ok());
+    }
 }
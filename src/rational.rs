@@ -0,0 +1,246 @@
+use std::{cmp::Ordering, convert::TryFrom, fmt::Display};
+
+use crate::FloatRounding;
+
+/// How an expression is computed, see [`crate::Roller::with_arithmetic()`].
+///
+/// A part of the expression involving a decimal number, like `1.5`, is always computed exactly,
+/// as a [`Rational`], and only the final total is rounded.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{Arithmetic, FloatRounding, Roller, ScriptedSource};
+///
+/// let arithmetic = Arithmetic {
+///     exact_division: true,
+///     rounding: FloatRounding::Nearest,
+/// };
+/// let roller = Roller::new("(1d6 * 1/3) * 3").unwrap().with_arithmetic(arithmetic);
+/// let res = roller.roll_with_source(&mut ScriptedSource::new(vec![5])).unwrap();
+/// // without exact division, 5 / 3 gives 1, and the total would be 3
+/// assert_eq!(5, res.as_single().unwrap().get_total());
+///
+/// let roller = Roller::new("1d6 / 4").unwrap().with_arithmetic(arithmetic);
+/// let res = roller.roll_with_source(&mut ScriptedSource::new(vec![5])).unwrap();
+/// assert_eq!(1, res.as_single().unwrap().get_total());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Arithmetic {
+    /// `/` gives an exact fraction instead of the integer division. `false` by default.
+    pub exact_division: bool,
+    /// How the final total is rounded when it's a fraction. Truncated toward zero by default.
+    pub rounding: FloatRounding,
+}
+
+/// An exact fraction, always reduced and with a positive denominator.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{FloatRounding, Rational};
+///
+/// let third = Rational::new(1, 3).unwrap();
+/// let one = third.checked_mul(Rational::from(3)).unwrap();
+/// assert_eq!(Rational::from(1), one);
+///
+/// let r = Rational::new(-10, 4).unwrap();
+/// assert_eq!("-5/2", r.to_string());
+/// assert_eq!(-3, r.round(FloatRounding::Floor));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "(i64, i64)", into = "(i64, i64)"))]
+pub struct Rational {
+    numer: i64,
+    denom: i64,
+}
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a.abs()
+}
+
+impl Rational {
+    /// Create the fraction `numer / denom`, `None` if `denom` is 0 or if the reduced fraction
+    /// doesn't fit.
+    pub fn new(numer: i64, denom: i64) -> Option<Self> {
+        Rational::reduce(numer as i128, denom as i128)
+    }
+
+    fn reduce(mut numer: i128, mut denom: i128) -> Option<Self> {
+        if denom == 0 {
+            return None;
+        }
+        if denom < 0 {
+            numer = -numer;
+            denom = -denom;
+        }
+        let gcd = gcd(numer, denom);
+        Some(Rational {
+            numer: i64::try_from(numer / gcd).ok()?,
+            denom: i64::try_from(denom / gcd).ok()?,
+        })
+    }
+
+    // a number written in decimal, like `-1.25`
+    pub(crate) fn from_decimal(s: &str) -> Option<Self> {
+        let (int, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let denom = 10_i128.checked_pow(fraction.len() as u32)?;
+        let numer: i128 = format!("{}{}", int, fraction).parse().ok()?;
+        Rational::reduce(numer, denom)
+    }
+
+    /// Get the numerator
+    pub fn numer(&self) -> i64 {
+        self.numer
+    }
+
+    /// Get the denominator, always positive
+    pub fn denom(&self) -> i64 {
+        self.denom
+    }
+
+    /// Says if the fraction is a whole number
+    pub fn is_integer(&self) -> bool {
+        self.denom == 1
+    }
+
+    /// Get the closest `f64`
+    pub fn to_f64(&self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    /// Get the fraction as an integer, rounded with `rounding`
+    pub fn round(&self, rounding: FloatRounding) -> i64 {
+        let (numer, denom) = (self.numer as i128, self.denom as i128);
+        let rounded = match rounding {
+            FloatRounding::Truncate => numer / denom,
+            FloatRounding::Floor => numer.div_euclid(denom),
+            FloatRounding::Ceil => -((-numer).div_euclid(denom)),
+            FloatRounding::Nearest => {
+                let half_away = (2 * numer.abs() + denom) / (2 * denom);
+                if numer < 0 {
+                    -half_away
+                } else {
+                    half_away
+                }
+            }
+        };
+        // a fraction is closer to zero than its numerator
+        rounded as i64
+    }
+
+    /// Add two fractions, `None` if the result doesn't fit.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        Rational::reduce(
+            self.numer as i128 * rhs.denom as i128 + rhs.numer as i128 * self.denom as i128,
+            self.denom as i128 * rhs.denom as i128,
+        )
+    }
+
+    /// Subtract two fractions, `None` if the result doesn't fit.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        Rational::reduce(
+            self.numer as i128 * rhs.denom as i128 - rhs.numer as i128 * self.denom as i128,
+            self.denom as i128 * rhs.denom as i128,
+        )
+    }
+
+    /// Multiply two fractions, `None` if the result doesn't fit.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Rational::reduce(
+            self.numer as i128 * rhs.numer as i128,
+            self.denom as i128 * rhs.denom as i128,
+        )
+    }
+
+    /// Divide two fractions, `None` if the divisor is zero or if the result doesn't fit.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        Rational::reduce(
+            self.numer as i128 * rhs.denom as i128,
+            self.denom as i128 * rhs.numer as i128,
+        )
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(i: i64) -> Self {
+        Rational { numer: i, denom: 1 }
+    }
+}
+
+impl TryFrom<(i64, i64)> for Rational {
+    type Error = &'static str;
+
+    fn try_from((numer, denom): (i64, i64)) -> Result<Self, Self::Error> {
+        Rational::new(numer, denom).ok_or("invalid fraction")
+    }
+}
+
+impl From<Rational> for (i64, i64) {
+    fn from(r: Rational) -> Self {
+        (r.numer, r.denom)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.numer as i128 * other.denom as i128).cmp(&(other.numer as i128 * self.denom as i128))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rational_test() {
+        let r = |n, d| Rational::new(n, d).unwrap();
+        assert_eq!(r(3, 2), r(-6, -4));
+        assert_eq!(None, Rational::new(1, 0));
+        assert_eq!(None, Rational::new(i64::MIN, -1));
+        assert_eq!(Some(r(-5, 4)), Rational::from_decimal("-1.25"));
+        assert_eq!(Some(r(3, 1)), Rational::from_decimal("+3.0"));
+        assert_eq!(Some(r(1, 3)), r(1, 6).checked_add(r(1, 6)));
+        assert_eq!(Some(r(-1, 6)), r(1, 6).checked_sub(r(1, 3)));
+        assert_eq!(Some(r(4, 1)), r(2, 3).checked_div(r(1, 6)));
+        assert_eq!(None, r(1, 2).checked_div(r(0, 1)));
+        assert_eq!(None, r(i64::MAX, 1).checked_mul(r(2, 1)));
+        assert!(r(1, 3) < r(1, 2));
+
+        use FloatRounding::*;
+        let rounded = |n, d| {
+            [Truncate, Floor, Ceil, Nearest]
+                .iter()
+                .map(|&rounding| r(n, d).round(rounding))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![2, 2, 3, 3], rounded(5, 2));
+        assert_eq!(vec![-2, -3, -2, -3], rounded(-5, 2));
+        assert_eq!(vec![0, 0, 1, 0], rounded(1, 3));
+        assert_eq!(vec![-3, -3, -3, -3], rounded(-3, 1));
+    }
+}
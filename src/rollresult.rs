@@ -10,8 +10,12 @@ pub use repeatedrollresult::*;
 pub use rollhistory::*;
 pub use singlerollresult::*;
 
+use crate::MarkdownFormatter;
+
 /// Distinguish between a simple roll and a repeated roll using `^`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum RollResultType {
     /// A single roll
     Single(SingleRollResult),
@@ -23,10 +27,17 @@ pub enum RollResultType {
 ///
 /// A `RollResult` contains either a single roll result, or if the roll is repeated, a list of the
 /// same roll different results. And a reason if needed.
+///
+/// Two results are equal if they have the same rolls and reason, whether they show their
+/// statistics or not.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RollResult {
     result: RollResultType,
     reason: Option<String>,
+    /// Show the statistics line of a repeated roll, with the threshold to count the rolls above.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stats: Option<Option<i64>>,
 }
 
 impl RollResult {
@@ -35,6 +46,7 @@ impl RollResult {
         RollResult {
             result: RollResultType::Single(r),
             reason: None,
+            stats: None,
         }
     }
 
@@ -43,6 +55,7 @@ impl RollResult {
         RollResult {
             result: RollResultType::Repeated(RepeatedRollResult { rolls: v, total }),
             reason: None,
+            stats: None,
         }
     }
 
@@ -56,11 +69,67 @@ impl RollResult {
         self.reason.as_ref()
     }
 
+    /// Add a line with the statistics of a repeated roll when it's turned into text: the lowest
+    /// and highest totals, their mean, median and sum, and how many are above `threshold` if
+    /// given. The sum is left out when the total of `^+` is shown. A single roll is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{PlainFormatter, Roller, ScriptedSource};
+    ///
+    /// let mut res = Roller::new("(1d20)^#>3")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![12, 5, 16]))
+    ///     .unwrap();
+    /// res.show_stats(Some(10));
+    /// assert_eq!(
+    ///     "[16] = 16\n[12] = 12\n[5] = 5\n\
+    ///      Min: 5, Max: 16, Mean: 11, Median: 12, Sum: 33, Above 10: 2\n",
+    ///     res.format(&PlainFormatter)
+    /// );
+    /// ```
+    pub fn show_stats(&mut self, threshold: Option<i64>) {
+        self.stats = Some(threshold);
+    }
+
+    // `Some` with the threshold if the statistics are shown
+    pub(crate) fn get_stats(&self) -> Option<Option<i64>> {
+        self.stats
+    }
+
     /// Return the result.
     pub fn get_result(&self) -> &RollResultType {
         &self.result
     }
 
+    /// Says if explosions or rerolls were stopped in any of the rolls because they reached the
+    /// limits, see [`crate::RollerLimits`].
+    pub fn is_truncated(&self) -> bool {
+        match &self.result {
+            RollResultType::Single(result) => result.is_truncated(),
+            RollResultType::Repeated(results) => results.iter().any(|r| r.is_truncated()),
+        }
+    }
+
+    /// Says if the roll, or any of the repeated rolls, is a critical success, see
+    /// [`SingleRollResult::has_critical_success()`].
+    pub fn has_critical_success(&self) -> bool {
+        match &self.result {
+            RollResultType::Single(result) => result.has_critical_success(),
+            RollResultType::Repeated(results) => results.iter().any(|r| r.has_critical_success()),
+        }
+    }
+
+    /// Says if the roll, or any of the repeated rolls, is a critical failure, see
+    /// [`SingleRollResult::has_critical_failure()`].
+    pub fn has_critical_failure(&self) -> bool {
+        match &self.result {
+            RollResultType::Single(result) => result.has_critical_failure(),
+            RollResultType::Repeated(results) => results.iter().any(|r| r.has_critical_failure()),
+        }
+    }
+
     /// If the result is a single roll, it will return it.
     pub fn as_single(&self) -> Option<&SingleRollResult> {
         match &self.result {
@@ -78,37 +147,16 @@ impl RollResult {
     }
 }
 
+// the statistics are only a way of showing the result
+impl PartialEq for RollResult {
+    fn eq(&self, other: &Self) -> bool {
+        self.result == other.result && self.reason == other.reason
+    }
+}
+
 impl Display for RollResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.result {
-            RollResultType::Single(roll_result) => {
-                write!(f, "{}", roll_result.to_string(true))?;
-                if let Some(reason) = &self.reason {
-                    write!(f, ", Reason: `{}`", reason)?;
-                }
-            }
-            RollResultType::Repeated(repeated_result) => match repeated_result.get_total() {
-                Some(total) => {
-                    (*repeated_result)
-                        .iter()
-                        .try_for_each(|res| writeln!(f, "`{}`", res.to_string_history()))?;
-                    write!(f, "Sum: **{}**", total)?;
-                    if let Some(reason) = &self.reason {
-                        write!(f, ", Reason: `{}`", reason)?;
-                    }
-                }
-                None => {
-                    (*repeated_result)
-                        .iter()
-                        .try_for_each(|res| writeln!(f, "{}", res.to_string(true)))?;
-                    if let Some(reason) = &self.reason {
-                        write!(f, "Reason: `{}`", reason)?;
-                    }
-                }
-            },
-        }
-
-        Ok(())
+        write!(f, "{}", self.format(&MarkdownFormatter))
     }
 }
 
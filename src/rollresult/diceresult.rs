@@ -1,7 +1,9 @@
 use std::ops::Deref;
 
 /// Used to mark a dice roll if its result is a critic.
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Critic {
     /// Normal result
     No,
@@ -11,13 +13,71 @@ pub enum Critic {
     Max,
 }
 
+/// Why a die is in the history of a roll
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DieOrigin {
+    /// Rolled as written in the expression, like the three dice of `3d6`
+    #[default]
+    Rolled,
+    /// Added by the explosion of another die, `parent` being its position in
+    /// [`super::SingleRollResult::dice()`]
+    Exploded {
+        /// Position of the die which exploded
+        parent: usize,
+    },
+    /// Rolled again in place of a die, `from` being the value first rolled by this die
+    Rerolled {
+        /// Value replaced by this die
+        from: u64,
+    },
+}
+
+/// How a die counts in the total of its roll
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Contribution {
+    /// Its value is added to the total
+    #[default]
+    Value,
+    /// One success, the target of `t` being reached
+    Success,
+    /// Two successes, the target of `tt` being reached
+    Double,
+    /// One failure, removing a success, the value of `f` not being exceeded
+    Failure,
+    /// Nothing, like a die under the target or a dropped die
+    Nothing,
+}
+
 /// Carry one dice result and a marker field to say if it the result is a min, max, or none.
-#[derive(Debug, Clone, Copy)]
+///
+/// It also says where the die comes from, with how many sides it was rolled, and how it counts in
+/// the total.
+///
+/// Two dice are equal if all their fields are. They are ordered by their value first.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiceResult {
     /// The side of the dice that was rolled
     pub res: u64,
     /// If the result was remarkable (critic)
     pub crit: Critic,
+    /// If the dice was dropped by a keep or drop option, like the lowest dice of `4d6 K3`, so it
+    /// doesn't count in the total
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dropped: bool,
+    /// Number of sides of the dice
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sides: u64,
+    /// If the dice was rolled, exploded or rerolled
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub origin: DieOrigin,
+    /// What the dice adds to the total
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub contribution: Contribution,
 }
 
 impl DiceResult {
@@ -35,27 +95,22 @@ impl DiceResult {
             } else {
                 Critic::No
             },
+            dropped: false,
+            sides,
+            origin: DieOrigin::Rolled,
+            contribution: Contribution::Value,
         }
     }
 }
 
-impl PartialEq for DiceResult {
-    fn eq(&self, other: &Self) -> bool {
-        self.res == other.res
-    }
-}
-
-impl Eq for DiceResult {}
-
-impl PartialOrd for DiceResult {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Ord for DiceResult {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.res.cmp(&other.res)
+impl DiceResult {
+    // a die with one side always rolls its highest and lowest value, which is not remarkable
+    pub(crate) fn critic(&self) -> Critic {
+        if self.sides == 1 {
+            Critic::No
+        } else {
+            self.crit
+        }
     }
 }
 
@@ -6,7 +6,8 @@ use crate::rollresult::SingleRollResult;
 ///
 /// Can store the sum of all the roll if asked to. Usually created through
 /// [`super::RollResult::new_repeated()`] function.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RepeatedRollResult {
     pub(crate) rolls: Vec<SingleRollResult>,
     pub(crate) total: Option<i64>,
@@ -25,4 +26,72 @@ impl RepeatedRollResult {
     pub fn get_total(&self) -> Option<i64> {
         self.total
     }
+
+    /// Get the lowest total of the rolls
+    pub fn min(&self) -> Option<i64> {
+        self.rolls.iter().map(|r| r.get_total()).min()
+    }
+
+    /// Get the highest total of the rolls
+    pub fn max(&self) -> Option<i64> {
+        self.rolls.iter().map(|r| r.get_total()).max()
+    }
+
+    /// Get the sum of the totals of the rolls, even if it wasn't asked with `^+`. `None` if it
+    /// overflows.
+    pub fn sum(&self) -> Option<i64> {
+        self.rolls
+            .iter()
+            .try_fold(0_i64, |acc, r| acc.checked_add(r.get_total()))
+    }
+
+    /// Get the average of the totals of the rolls
+    pub fn mean(&self) -> Option<f64> {
+        if self.rolls.is_empty() {
+            return None;
+        }
+        let sum: i128 = self.rolls.iter().map(|r| r.get_total() as i128).sum();
+        Some(sum as f64 / self.rolls.len() as f64)
+    }
+
+    /// Get the median of the totals of the rolls, the average of the two middle ones if there's an
+    /// even number of rolls.
+    pub fn median(&self) -> Option<f64> {
+        let mut totals = self.rolls.iter().map(|r| r.get_total()).collect::<Vec<_>>();
+        totals.sort_unstable();
+        let middle = totals.len() / 2;
+        match totals.len() {
+            0 => None,
+            len if len % 2 == 1 => Some(totals[middle] as f64),
+            _ => Some((totals[middle - 1] as f64 + totals[middle] as f64) / 2.0),
+        }
+    }
+
+    /// Count the rolls with a total strictly greater than `threshold`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{Roller, ScriptedSource};
+    ///
+    /// let res = Roller::new("(4d6 d1)^3")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![
+    ///         6, 6, 5, 1, // 17
+    ///         3, 3, 2, 2, // 8
+    ///         4, 4, 4, 6, // 14
+    ///     ]))
+    ///     .unwrap();
+    /// let rep = res.as_repeated().unwrap();
+    /// assert_eq!((Some(8), Some(17)), (rep.min(), rep.max()));
+    /// assert_eq!((Some(13.0), Some(14.0)), (rep.mean(), rep.median()));
+    /// assert_eq!(Some(39), rep.sum());
+    /// assert_eq!(2, rep.count_above(12));
+    /// ```
+    pub fn count_above(&self, threshold: i64) -> usize {
+        self.rolls
+            .iter()
+            .filter(|r| r.get_total() > threshold)
+            .count()
+    }
 }
@@ -1,9 +1,11 @@
 use std::fmt::Display;
 
-use crate::rollresult::DiceResult;
+use crate::{rollresult::DiceResult, PlainFormatter};
 
 /// Carry a constant, either an `i64` or a `f64`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Value {
     /// Integer variant
     Int(i64),
@@ -11,6 +13,23 @@ pub enum Value {
     Float(f64),
 }
 
+/// How a float or a fraction is turned into an integer, see [`Value::round()`] and
+/// [`crate::Arithmetic::rounding`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum FloatRounding {
+    /// Toward zero, like the total of a roll: 2.5 gives 2 and -2.5 gives -2
+    #[default]
+    Truncate,
+    /// Toward negative infinity: 2.5 gives 2 and -2.5 gives -3
+    Floor,
+    /// Toward positive infinity: 2.5 gives 3 and -2.5 gives -2
+    Ceil,
+    /// To the closest integer, halfway away from zero: 2.5 gives 3 and -2.5 gives -3
+    Nearest,
+}
+
 impl Value {
     /// Get the value as `i64`.
     pub fn get_value(&self) -> i64 {
@@ -19,6 +38,37 @@ impl Value {
             Value::Float(f) => f as i64,
         }
     }
+
+    /// Get the value as `f64`.
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            Value::Int(i) => i as f64,
+            Value::Float(f) => f,
+        }
+    }
+
+    /// Get the value as `i64`, rounded with `rounding`. `None` if it doesn't fit.
+    pub fn round(&self, rounding: FloatRounding) -> Option<i64> {
+        match *self {
+            Value::Int(i) => Some(i),
+            Value::Float(f) => float_to_i64(match rounding {
+                FloatRounding::Truncate => f,
+                FloatRounding::Floor => f.floor(),
+                FloatRounding::Ceil => f.ceil(),
+                FloatRounding::Nearest => f.round(),
+            }),
+        }
+    }
+}
+
+fn float_to_i64(f: f64) -> Option<i64> {
+    let f = f.trunc();
+    // `i64::MAX as f64` is rounded up to 2^63, which doesn't fit
+    if f.is_finite() && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        Some(f as i64)
+    } else {
+        None
+    }
 }
 
 impl Display for Value {
@@ -37,13 +87,18 @@ impl Display for Value {
 /// between different dices. Ex: for `1d6 + 1d6`, we will have a [`RollHistory::Roll`] followed by
 /// [`RollHistory::Separator`] and another [`RollHistory::Roll`].
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum RollHistory {
     /// Rolls which include rerolls.
     /// Should be followed by a Roll with the final results.
+    #[cfg_attr(feature = "serde", serde(rename = "rerolls"))]
     ReRolls(Vec<Vec<DiceResult>>),
     /// A roll with normal dices
     Roll(Vec<DiceResult>),
+    /// Dice given a fixed value instead of being rolled, see [`crate::Roller::roll_fixed()`]
+    Fixed(Vec<DiceResult>),
     /// A roll with Fudge dices
     Fudge(Vec<u64>),
     /// Was not a roll, but just a value
@@ -54,65 +109,64 @@ pub enum RollHistory {
     OpenParenthesis,
     /// Close parenthesis
     CloseParenthesis,
+    /// Label of the preceding roll, value or parenthesis
+    Label(String),
+    /// Explosions or rerolls were stopped by [`crate::RollerLimits`]
+    Truncated,
 }
 
 impl Display for RollHistory {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            RollHistory::ReRolls(v) => {
-                let s2 = v
-                    .iter()
-                    .map(|r| {
-                        r.iter()
-                            .map(|r| r.res.to_string())
-                            .collect::<Vec<_>>()
-                            .join(" -> ")
-                    })
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("[{}] -> ", s2)
-            }
-            RollHistory::Roll(v) => {
-                let s2 = v
+        write!(f, "{}", self.format(&PlainFormatter))
+    }
+}
+
+// Operators found in `RollHistory::Separator`
+#[cfg(feature = "serde")]
+const SEPARATORS: [&str; 4] = [" + ", " - ", " * ", " / "];
+
+// `RollHistory` with an owned separator, as a `&'static str` can't be deserialized
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RollHistoryRepr {
+    #[serde(rename = "rerolls")]
+    ReRolls(Vec<Vec<DiceResult>>),
+    Roll(Vec<DiceResult>),
+    Fixed(Vec<DiceResult>),
+    Fudge(Vec<u64>),
+    Value(Value),
+    Separator(String),
+    OpenParenthesis,
+    CloseParenthesis,
+    Label(String),
+    Truncated,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RollHistory {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        Ok(match RollHistoryRepr::deserialize(deserializer)? {
+            RollHistoryRepr::ReRolls(v) => RollHistory::ReRolls(v),
+            RollHistoryRepr::Roll(v) => RollHistory::Roll(v),
+            RollHistoryRepr::Fixed(v) => RollHistory::Fixed(v),
+            RollHistoryRepr::Fudge(v) => RollHistory::Fudge(v),
+            RollHistoryRepr::Value(v) => RollHistory::Value(v),
+            RollHistoryRepr::Separator(sep) => RollHistory::Separator(
+                SEPARATORS
                     .iter()
-                    .map(|r| r.res.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("[{}]", s2)
-            }
-            RollHistory::Fudge(v) => {
-                let mut s = String::new();
-                s.push('[');
-                let len = v.len();
-                v.iter().enumerate().for_each(|(i, r)| {
-                    let r = if *r <= 2 {
-                        "-"
-                    } else if *r <= 4 {
-                        "▢"
-                    } else {
-                        "+"
-                    };
-                    s.push_str(r);
-                    if i < len - 1 {
-                        s.push_str(", ");
-                    }
-                });
-                s.push(']');
-                s
-            }
-            RollHistory::Value(v) => {
-                let mut s = String::new();
-                s.push_str(&v.to_string());
-                s
-            }
-            RollHistory::Separator(sep) => {
-                let mut s = String::new();
-                s.push_str(sep);
-                s
-            }
-            RollHistory::OpenParenthesis => "(".to_string(),
-            RollHistory::CloseParenthesis => ")".to_string(),
-        };
-        write!(f, "{}", s)
+                    .find(|s| **s == sep)
+                    .ok_or_else(|| D::Error::custom(format!("unknown separator `{}`", sep)))?,
+            ),
+            RollHistoryRepr::OpenParenthesis => RollHistory::OpenParenthesis,
+            RollHistoryRepr::CloseParenthesis => RollHistory::CloseParenthesis,
+            RollHistoryRepr::Label(label) => RollHistory::Label(label),
+            RollHistoryRepr::Truncated => RollHistory::Truncated,
+        })
     }
 }
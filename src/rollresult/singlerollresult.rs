@@ -1,12 +1,19 @@
+use std::{cmp::Reverse, convert::TryFrom};
+
 use crate::{
-    error::Result, parser::TotalModifier, rollresult::DiceResult, rollresult::RollHistory,
+    error::{Result, RollError, Span},
+    parser::TotalModifier,
+    rollresult::RollHistory,
     rollresult::Value,
+    rollresult::{Contribution, Critic, DiceResult, DieOrigin},
+    FloatRounding, MarkdownFormatter, PlainFormatter, Rational,
 };
 
 /// Carry the result of one roll and an history of the steps taken.
 ///
 /// Usually created through [`super::RollResult::new_single()`] function.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SingleRollResult {
     /// Result of the roll. In the case of option `t` and/or `f` used, it's the number of `success -
     /// failure`
@@ -14,8 +21,14 @@ pub struct SingleRollResult {
     /// History of the steps taken that lead to this result.
     history: Vec<RollHistory>,
     /// Internal usage field to avoid computing a total if it's already done.
+    #[cfg_attr(feature = "serde", serde(skip))]
     dirty: bool,
-    constant: Option<f64>,
+    /// Exact value of the total when a fraction is involved, `total` being rounded.
+    exact: Option<Rational>,
+    /// Subtotals of the labelled sub-expressions, in order of appearance.
+    labels: Vec<(String, i64)>,
+    /// Label of this roll when it's one iteration of a repeated roll.
+    label: Option<String>,
 }
 
 impl SingleRollResult {
@@ -25,7 +38,9 @@ impl SingleRollResult {
             total: 0,
             history: Vec::new(),
             dirty: true,
-            constant: None,
+            exact: None,
+            labels: Vec::new(),
+            label: None,
         }
     }
 
@@ -35,17 +50,22 @@ impl SingleRollResult {
             total,
             history: vec![RollHistory::Value(Value::Int(total))],
             dirty: false,
-            constant: None,
+            exact: None,
+            labels: Vec::new(),
+            label: None,
         }
     }
 
-    /// Create a `SingleRollResult` with already a total. Used to carry float constant value.
-    pub(crate) fn with_float(f: f64) -> Self {
+    /// Create a `SingleRollResult` with already a total. Used to carry decimal constant value,
+    /// `literal` being how it was written.
+    pub(crate) fn with_fraction(value: Rational, literal: f64) -> Self {
         Self {
-            total: f as i64,
-            history: vec![RollHistory::Value(Value::Float(f))],
+            total: value.round(FloatRounding::Truncate),
+            history: vec![RollHistory::Value(Value::Float(literal))],
             dirty: false,
-            constant: Some(f),
+            exact: Some(value),
+            labels: Vec::new(),
+            label: None,
         }
     }
 
@@ -56,7 +76,9 @@ impl SingleRollResult {
             total: total as i64,
             history: vec![RollHistory::Roll(history)],
             dirty: false,
-            constant: None,
+            exact: None,
+            labels: Vec::new(),
+            label: None,
         }
     }
 
@@ -68,7 +90,7 @@ impl SingleRollResult {
     /// Add a step in the history
     pub(crate) fn add_history(&mut self, mut history: Vec<DiceResult>, is_fudge: bool) {
         self.dirty = true;
-        history.sort_unstable_by(|a, b| b.cmp(a));
+        history.sort_unstable_by_key(|d| Reverse(d.res));
         self.history.push(if is_fudge {
             RollHistory::Fudge(history.iter().map(|r| r.res).collect())
         } else {
@@ -78,43 +100,200 @@ impl SingleRollResult {
 
     pub(crate) fn add_rerolled_history(&mut self, mut history: Vec<Vec<DiceResult>>) {
         self.dirty = true;
-        history.sort_unstable_by(|a, b| b.cmp(a));
+        history.sort_unstable_by(|a, b| b.iter().map(|d| d.res).cmp(a.iter().map(|d| d.res)));
         self.history.push(RollHistory::ReRolls(history));
     }
 
+    // the dice were given a fixed value instead of being rolled, fudge dice keep their symbols.
+    // A fixed value is not a natural roll, so it's never a critic.
+    pub(crate) fn mark_fixed(&mut self) {
+        self.history.iter_mut().for_each(|h| {
+            if let RollHistory::Roll(r) = h {
+                r.iter_mut().for_each(|die| die.crit = Critic::No);
+                *h = RollHistory::Fixed(std::mem::take(r));
+            }
+        });
+    }
+
+    /// Iterate over the dice of the result, in the order of the history, with where they come
+    /// from, their sides and how they count in the total.
+    ///
+    /// The dice replaced by a reroll are not given, the new dice being marked as
+    /// [`DieOrigin::Rerolled`], and fudge dice are not [`DiceResult`] so they are not given either.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{DieOrigin, Roller, ScriptedSource};
+    ///
+    /// let res = Roller::new("2d6 e6 K2")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![6, 2, 4]))
+    ///     .unwrap();
+    /// let dice: Vec<_> = res.as_single().unwrap().dice().collect();
+    /// // the first die exploded into the third one, the second one was dropped
+    /// assert_eq!(3, dice.len());
+    /// assert_eq!(DieOrigin::Exploded { parent: 0 }, dice[2].origin);
+    /// assert!(dice[1].dropped);
+    /// ```
+    pub fn dice(&self) -> impl Iterator<Item = &DiceResult> {
+        self.history.iter().flat_map(|h| match h {
+            RollHistory::Roll(r) | RollHistory::Fixed(r) => r.as_slice(),
+            _ => &[],
+        })
+    }
+
+    /// Says if a single die counts in the total and it rolled its highest value, like a natural 20
+    /// on `1d20 + 5`, or on `2d20 K1` for the kept die.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{Roller, ScriptedSource};
+    ///
+    /// let roller = Roller::new("2d20 K1 + 5").unwrap();
+    /// let res = roller
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![1, 20]))
+    ///     .unwrap();
+    /// assert!(res.has_critical_success());
+    /// // the 1 was dropped
+    /// assert!(!res.has_critical_failure());
+    /// ```
+    pub fn has_critical_success(&self) -> bool {
+        self.single_counted_die()
+            .is_some_and(|die| die.critic() == Critic::Max)
+    }
+
+    /// Says if a single die counts in the total and it rolled its lowest value, like a natural 1
+    /// on `1d20 + 5`, or on `2d20 k1` for the kept die.
+    pub fn has_critical_failure(&self) -> bool {
+        self.single_counted_die()
+            .is_some_and(|die| die.critic() == Critic::Min)
+    }
+
+    // the die counted in the total if there's only one
+    fn single_counted_die(&self) -> Option<&DiceResult> {
+        let mut counted = self.dice().filter(|die| !die.dropped);
+        match (counted.next(), counted.next()) {
+            (Some(die), None) => Some(die),
+            _ => None,
+        }
+    }
+
+    // positions in `dice()` of the dice of the last step reaching `value`, which explode
+    pub(crate) fn exploding_dice(&self, value: u64) -> Vec<usize> {
+        let count = self.dice().count();
+        let last = self.history.iter().rev().find_map(|h| match h {
+            RollHistory::Roll(r) | RollHistory::Fixed(r) => Some(r),
+            _ => None,
+        });
+        match last {
+            Some(last) => last
+                .iter()
+                .enumerate()
+                .filter(|(_, die)| die.res >= value)
+                .map(|(i, _)| count - last.len() + i)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub(crate) fn add_truncated(&mut self) {
+        self.history.push(RollHistory::Truncated);
+    }
+
+    /// Says if explosions or rerolls were stopped because they reached the limits, see
+    /// [`crate::RollerLimits`].
+    pub fn is_truncated(&self) -> bool {
+        self.history
+            .iter()
+            .any(|h| matches!(h, RollHistory::Truncated))
+    }
+
+    /// Label the whole result, its total becoming the subtotal of this label.
+    pub(crate) fn add_label(&mut self, label: String) {
+        self.labels.retain(|(l, _)| *l != label);
+        self.labels.push((label.clone(), self.total));
+        self.history.push(RollHistory::Label(label));
+    }
+
+    /// Get the subtotals of each labelled sub-expression, in order of appearance.
+    ///
+    /// For `1d8[slashing] + 2d6[fire]`, it will be `[("slashing", x), ("fire", y)]`. The subtotals
+    /// of a side of `*` or `/` are scaled by the other side, `1d8[slashing] * 2` doubles the
+    /// subtotal. Multiplying two labelled values, or dividing by a labelled value, is rejected with
+    /// [`crate::RollError::InvalidLabelOperation`].
+    pub fn get_label_totals(&self) -> &[(String, i64)] {
+        &self.labels
+    }
+
+    /// Get the subtotal of a label, if it was used in the expression.
+    pub fn get_label_total(&self, label: &str) -> Option<i64> {
+        self.labels
+            .iter()
+            .find(|(l, _)| l == label)
+            .map(|(_, total)| *total)
+    }
+
+    pub(crate) fn set_label(&mut self, label: String) {
+        self.label = Some(label);
+    }
+
+    /// Get the label of this roll, if it's one labelled iteration of a repeated roll.
+    ///
+    /// `(1d20 + 4)^2 : goblin A, goblin B` will label the first roll `goblin A` and the second
+    /// one `goblin B`.
+    pub fn get_label(&self) -> Option<&String> {
+        self.label.as_ref()
+    }
+
     pub(crate) fn add_parenthesis(&mut self) {
         self.history.insert(0, RollHistory::OpenParenthesis);
         self.history.push(RollHistory::CloseParenthesis);
     }
 
-    /// Compute the total value according to some modifier
-    pub(crate) fn compute_total(&mut self, modifier: TotalModifier) -> Result<i64> {
+    /// Compute the total value according to some modifier, `span` being the dice expression
+    pub(crate) fn compute_total(&mut self, modifier: TotalModifier, span: Span) -> Result<i64> {
         if self.dirty {
             self.dirty = false;
-            let mut flat = self.history.iter().fold(Vec::new(), |mut acc, h| {
+            let overflow = RollError::Overflow { span };
+            // each value, with the position of the die in the history
+            let mut flat = Vec::new();
+            for (i, h) in self.history.iter_mut().enumerate() {
                 match h {
-                    RollHistory::Roll(r) => {
-                        let mut c = r.iter().map(|u| u.res as i64).collect();
-                        acc.append(&mut c);
+                    RollHistory::Roll(r) | RollHistory::Fixed(r) => {
+                        for (j, u) in r.iter_mut().enumerate() {
+                            u.dropped = false;
+                            u.contribution = contribution(&modifier, u.res);
+                            let value = i64::try_from(u.res).map_err(|_| overflow.clone())?;
+                            flat.push((value, Some((i, j))));
+                        }
                     }
                     RollHistory::Fudge(r) => {
-                        let mut c = r.iter().map(|u| *u as i64).collect();
-                        acc.append(&mut c);
+                        for u in r.iter() {
+                            flat.push((i64::try_from(*u).map_err(|_| overflow.clone())?, None));
+                        }
                     }
-                    RollHistory::Value(v) => acc.push(v.get_value()),
+                    RollHistory::Value(v) => flat.push((v.get_value(), None)),
                     _ => (),
                 };
-                acc
-            });
-            flat.sort_unstable();
-            let flat = flat;
+            }
+            // among equal values, the dice shown first are kept first
+            match modifier {
+                TotalModifier::KeepLo(_) | TotalModifier::DropHi(_) => flat.sort_unstable(),
+                _ => flat.sort_unstable_by_key(|&(value, pos)| (value, Reverse(pos))),
+            }
             match modifier {
                 TotalModifier::KeepHi(n)
                 | TotalModifier::KeepLo(n)
                 | TotalModifier::DropHi(n)
                 | TotalModifier::DropLo(n) => {
                     if n > flat.len() {
-                        return Err("Not enough dice to keep or drop".into());
+                        return Err(RollError::NotEnoughDice {
+                            span,
+                            requested: n,
+                            available: flat.len(),
+                        });
                     }
                 }
                 TotalModifier::None(_)
@@ -123,37 +302,37 @@ impl SingleRollResult {
                 | TotalModifier::Fudge => (),
             }
 
-            let slice = match modifier {
-                TotalModifier::KeepHi(n) => &flat[flat.len() - n..],
-                TotalModifier::KeepLo(n) => &flat[..n],
-                TotalModifier::DropHi(n) => &flat[..flat.len() - n],
-                TotalModifier::DropLo(n) => &flat[n..],
+            let kept = match modifier {
+                TotalModifier::KeepHi(n) => flat.len() - n..flat.len(),
+                TotalModifier::KeepLo(n) => 0..n,
+                TotalModifier::DropHi(n) => 0..flat.len() - n,
+                TotalModifier::DropLo(n) => n..flat.len(),
                 TotalModifier::None(_)
                 | TotalModifier::TargetFailureDouble(_, _, _)
                 | TotalModifier::TargetEnum(_)
-                | TotalModifier::Fudge => flat.as_slice(),
+                | TotalModifier::Fudge => 0..flat.len(),
             };
+            for (idx, (_, pos)) in flat.iter().enumerate() {
+                if let (false, Some((i, j))) = (kept.contains(&idx), pos) {
+                    if let RollHistory::Roll(r) | RollHistory::Fixed(r) = &mut self.history[*i] {
+                        r[*j].dropped = true;
+                        r[*j].contribution = Contribution::Nothing;
+                    }
+                }
+            }
+            let slice: Vec<i64> = flat[kept].iter().map(|(value, _)| *value).collect();
 
             self.total = match modifier {
-                TotalModifier::TargetFailureDouble(t, f, d) => slice.iter().fold(0, |acc, &x| {
-                    let x = x as u64;
-                    if d > 0 && x >= d {
-                        acc + 2
-                    } else if t > 0 && x >= t {
-                        acc + 1
-                    } else if f > 0 && x <= f {
-                        acc - 1
-                    } else {
-                        acc
-                    }
-                }),
-                TotalModifier::TargetEnum(v) => slice.iter().fold(0, |acc, &x| {
-                    if v.contains(&(x as u64)) {
-                        acc + 1
-                    } else {
-                        acc
-                    }
-                }),
+                TotalModifier::TargetFailureDouble(_, _, _) | TotalModifier::TargetEnum(_) => {
+                    slice.iter().fold(0, |acc, &x| {
+                        acc + match contribution(&modifier, x as u64) {
+                            Contribution::Double => 2,
+                            Contribution::Success => 1,
+                            Contribution::Failure => -1,
+                            Contribution::Value | Contribution::Nothing => 0,
+                        }
+                    })
+                }
                 TotalModifier::Fudge => slice.iter().fold(0, |acc, &x| {
                     if x <= 2 {
                         acc - 1
@@ -163,7 +342,10 @@ impl SingleRollResult {
                         acc + 1
                     }
                 }),
-                _ => slice.iter().sum::<i64>(),
+                _ => slice
+                    .iter()
+                    .try_fold(0_i64, |acc, &x| acc.checked_add(x))
+                    .ok_or(overflow)?,
             };
         }
 
@@ -175,129 +357,471 @@ impl SingleRollResult {
         self.total
     }
 
+    /// Get the exact value of the result: an integer, or a float if a float was involved like in
+    /// `3d6 * 1.5 + 0.5`. [`SingleRollResult::get_total()`] gives this value rounded, and
+    /// [`Value::round()`] rounds it another way. [`SingleRollResult::get_exact()`] gives it
+    /// without representation error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{FloatRounding, Roller, ScriptedSource, Value};
+    ///
+    /// let res = Roller::new("3d6 * 1.5 + 0.5")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![1, 2, 4]))
+    ///     .unwrap();
+    /// let res = res.as_single().unwrap();
+    /// // 7 * 1.5 = 10.5 is not truncated before adding 0.5
+    /// assert_eq!(Value::Float(11.0), res.get_value());
+    /// assert_eq!(11, res.get_total());
+    ///
+    /// let res = Roller::new("1d6 * 1.5")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![3]))
+    ///     .unwrap();
+    /// let value = res.as_single().unwrap().get_value();
+    /// assert_eq!(Some(4), value.round(FloatRounding::Truncate));
+    /// assert_eq!(Some(5), value.round(FloatRounding::Nearest));
+    /// ```
+    pub fn get_value(&self) -> Value {
+        match self.exact {
+            Some(exact) => Value::Float(exact.to_f64()),
+            None => Value::Int(self.total),
+        }
+    }
+
+    /// Get the exact value of the result as a fraction, which a decimal number or an exact
+    /// division may give. [`SingleRollResult::get_total()`] gives this value rounded with
+    /// [`Arithmetic::rounding`](crate::Arithmetic::rounding), and [`Rational::round()`] rounds it
+    /// another way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{FloatRounding, Rational, Roller, ScriptedSource};
+    ///
+    /// let res = Roller::new("1d6 * 0.7 / 0.1")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![3]))
+    ///     .unwrap();
+    /// // 0.7 / 0.1 is exactly 7
+    /// assert_eq!(Rational::from(21), res.as_single().unwrap().get_exact());
+    ///
+    /// let res = Roller::new("1d6 * 1.5")
+    ///     .unwrap()
+    ///     .roll_with_source(&mut ScriptedSource::new(vec![3]))
+    ///     .unwrap();
+    /// let value = res.as_single().unwrap().get_exact();
+    /// assert_eq!(Rational::new(9, 2), Some(value));
+    /// assert_eq!(5, value.round(FloatRounding::Nearest));
+    /// ```
+    pub fn get_exact(&self) -> Rational {
+        self.exact.unwrap_or_else(|| Rational::from(self.total))
+    }
+
     /// Says if the used value for math operation is 0
     ///
-    /// If there's a constant stored, we'll use it and if not, `total` is used instead
+    /// If there's an exact value stored, we'll use it and if not, `total` is used instead
     pub fn is_zero(&self) -> bool {
-        if let Some(c) = self.constant {
-            c == 0.0
-        } else {
-            self.total == 0
+        self.get_exact().numer() == 0
+    }
+
+    // round the total of the whole expression, intermediate totals being truncated
+    pub(crate) fn round_total(&mut self, rounding: FloatRounding) {
+        if let Some(exact) = self.exact {
+            self.total = exact.round(rounding);
         }
     }
 
     /// Turn the vector of `RollHistory` to a `String`
     pub fn to_string_history(&self) -> String {
-        self.history.iter().fold(String::new(), |mut s, v| {
-            s.push_str(v.to_string().as_str());
-            s
-        })
+        self.format_history(&PlainFormatter)
     }
 
     /// Turn the `RollResult` to a readable String, with or without markdown formatting.
+    ///
+    /// See [`SingleRollResult::format()`] for other formats.
     pub fn to_string(&self, md: bool) -> String {
-        if self.history.is_empty() {
-            if md {
-                format!("`{}`", self.total)
+        if md {
+            self.format(&MarkdownFormatter)
+        } else {
+            self.format(&PlainFormatter)
+        }
+    }
+}
+
+// how a die of value `res` counts in a total computed with `modifier`
+fn contribution(modifier: &TotalModifier, res: u64) -> Contribution {
+    match modifier {
+        TotalModifier::TargetFailureDouble(t, f, d) => {
+            if *d > 0 && res >= *d {
+                Contribution::Double
+            } else if *t > 0 && res >= *t {
+                Contribution::Success
+            } else if *f > 0 && res <= *f {
+                Contribution::Failure
             } else {
-                format!("{}", self.total)
+                Contribution::Nothing
+            }
+        }
+        TotalModifier::TargetEnum(v) => {
+            if v.contains(&res) {
+                Contribution::Success
+            } else {
+                Contribution::Nothing
             }
-        } else {
-            let s = self.to_string_history();
-            format!(
-                "{1}{0}{1} = {2}{3}{2}",
-                s,
-                if md { "`" } else { "" },
-                if md { "**" } else { "" },
-                self.get_total()
-            )
         }
+        _ => Contribution::Value,
     }
 }
 
 fn merge_history(left: &mut SingleRollResult, right: &mut SingleRollResult, op: &'static str) {
     if !right.history.is_empty() {
+        // the parents of the exploded dice of `right` come after the dice of `left`
+        let offset = left.dice().count();
+        for h in right.history.iter_mut() {
+            let dice = match h {
+                RollHistory::Roll(r) | RollHistory::Fixed(r) => r.iter_mut().collect(),
+                RollHistory::ReRolls(chains) => chains.iter_mut().flatten().collect(),
+                _ => Vec::new(),
+            };
+            for die in dice {
+                if let DieOrigin::Exploded { parent } = &mut die.origin {
+                    *parent += offset;
+                }
+            }
+        }
         left.history.push(RollHistory::Separator(op));
         left.history.append(&mut right.history);
     }
 }
 
+// How an operator combines two results: `None` from an operation means an overflow. The labelled
+// subtotals follow `+` and `-`, and are scaled by `*` and `/`.
+struct Operator {
+    symbol: &'static str,
+    int_op: fn(i64, i64) -> Option<i64>,
+    exact_op: fn(Rational, Rational) -> Option<Rational>,
+    label_add: fn(i64, i64) -> Option<i64>,
+    is_additive: bool,
+    is_commutative: bool,
+}
+
+const ADD: Operator = Operator {
+    symbol: " + ",
+    int_op: i64::checked_add,
+    exact_op: Rational::checked_add,
+    label_add: i64::checked_add,
+    is_additive: true,
+    is_commutative: true,
+};
+
+const SUB: Operator = Operator {
+    symbol: " - ",
+    int_op: i64::checked_sub,
+    exact_op: Rational::checked_sub,
+    is_commutative: false,
+    ..ADD
+};
+
+const MUL: Operator = Operator {
+    symbol: " * ",
+    int_op: i64::checked_mul,
+    exact_op: Rational::checked_mul,
+    is_additive: false,
+    ..ADD
+};
+
+const DIV: Operator = Operator {
+    symbol: " / ",
+    int_op: i64::checked_div,
+    exact_op: Rational::checked_div,
+    is_additive: false,
+    is_commutative: false,
+    ..ADD
+};
+
+// The `std::ops` operators saturate: a total that overflows is clamped to `i64::MIN` or
+// `i64::MAX`, and a division by zero gives `i64::MAX`, `i64::MIN` or 0 following the sign of the
+// dividend. A fraction that doesn't fit anymore is replaced by its `f64` value, truncated and
+// clamped the same way.
+fn saturating_fraction(value: f64) -> Option<Rational> {
+    // `as` clamps to the bounds of `i64` and gives 0 for NaN
+    Some(Rational::from(value.trunc() as i64))
+}
+
+fn saturating_div(l: i64, r: i64) -> i64 {
+    match r {
+        0 => match l.signum() {
+            1 => i64::MAX,
+            -1 => i64::MIN,
+            _ => 0,
+        },
+        _ => l.saturating_div(r),
+    }
+}
+
+const SATURATING_ADD: Operator = Operator {
+    int_op: |l, r| Some(l.saturating_add(r)),
+    exact_op: |l, r| {
+        l.checked_add(r)
+            .or_else(|| saturating_fraction(l.to_f64() + r.to_f64()))
+    },
+    label_add: |l, r| Some(l.saturating_add(r)),
+    ..ADD
+};
+
+const SATURATING_SUB: Operator = Operator {
+    int_op: |l, r| Some(l.saturating_sub(r)),
+    exact_op: |l, r| {
+        l.checked_sub(r)
+            .or_else(|| saturating_fraction(l.to_f64() - r.to_f64()))
+    },
+    ..SATURATING_ADD
+};
+
+const SATURATING_MUL: Operator = Operator {
+    int_op: |l, r| Some(l.saturating_mul(r)),
+    exact_op: |l, r| {
+        l.checked_mul(r)
+            .or_else(|| saturating_fraction(l.to_f64() * r.to_f64()))
+    },
+    ..SATURATING_ADD
+};
+
+const SATURATING_DIV: Operator = Operator {
+    int_op: |l, r| Some(saturating_div(l, r)),
+    exact_op: |l, r| {
+        l.checked_div(r)
+            .or_else(|| saturating_fraction(l.to_f64() / r.to_f64()))
+    },
+    ..SATURATING_ADD
+};
+
+// Apply an operation the way totals are combined: if a fraction is involved, the exact operation
+// is used and its result kept along the truncated total. `None` if the result overflows.
+fn apply_op(
+    lhs: (i64, Option<Rational>),
+    rhs: (i64, Option<Rational>),
+    op: &Operator,
+) -> Option<(i64, Option<Rational>)> {
+    let exact = match (lhs.1, rhs.1) {
+        (None, None) => return (op.int_op)(lhs.0, rhs.0).map(|total| (total, None)),
+        (lexact, rexact) => (op.exact_op)(
+            lexact.unwrap_or_else(|| lhs.0.into()),
+            rexact.unwrap_or_else(|| rhs.0.into()),
+        )?,
+    };
+    Some((exact.round(FloatRounding::Truncate), Some(exact)))
+}
+
+fn add_label_total(
+    labels: &mut Vec<(String, i64)>,
+    label: String,
+    total: i64,
+    op: &Operator,
+) -> Option<()> {
+    match labels.iter_mut().find(|(l, _)| *l == label) {
+        Some((_, t)) => *t = (op.label_add)(*t, total)?,
+        None => labels.push((label, total)),
+    }
+    Some(())
+}
+
+// Labelled subtotals follow `+` and `-`. For `*` and `/`, the subtotals of one side are scaled by
+// the other side, as long as this other side has no label itself. Otherwise the subtotals can't
+// be related to the total and are dropped: the roller rejects these expressions beforehand.
+fn merge_labels(
+    left: &mut SingleRollResult,
+    right: &mut SingleRollResult,
+    op: &Operator,
+) -> Option<()> {
+    let scale = |labels: &mut Vec<(String, i64)>, other: &SingleRollResult| {
+        labels.iter_mut().try_for_each(|(_, t)| {
+            *t = apply_op((*t, None), (other.total, other.exact), op)?.0;
+            Some(())
+        })
+    };
+    if op.is_additive {
+        let right_labels = std::mem::take(&mut right.labels);
+        for (l, t) in right_labels.into_iter() {
+            add_label_total(&mut left.labels, l, (op.int_op)(0, t)?, op)?;
+        }
+    } else if right.labels.is_empty() {
+        scale(&mut left.labels, right)?;
+    } else if left.labels.is_empty() && op.is_commutative {
+        let mut right_labels = std::mem::take(&mut right.labels);
+        scale(&mut right_labels, left)?;
+        left.labels = right_labels;
+    } else {
+        left.labels.clear();
+        right.labels.clear();
+    }
+    Some(())
+}
+
+fn combine(
+    mut lhs: SingleRollResult,
+    mut rhs: SingleRollResult,
+    op: &Operator,
+) -> Option<SingleRollResult> {
+    let (total, exact) = apply_op((lhs.total, lhs.exact), (rhs.total, rhs.exact), op)?;
+    merge_labels(&mut lhs, &mut rhs, op)?;
+    merge_history(&mut lhs, &mut rhs, op.symbol);
+    Some(SingleRollResult {
+        total,
+        history: lhs.history,
+        dirty: false,
+        exact,
+        labels: lhs.labels,
+        label: None,
+    })
+}
+
+impl SingleRollResult {
+    /// Add two results, `None` if the total overflows.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        combine(self, rhs, &ADD)
+    }
+
+    /// Subtract two results, `None` if the total overflows.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        combine(self, rhs, &SUB)
+    }
+
+    /// Multiply two results, `None` if the total overflows.
+    ///
+    /// If both results have labelled subtotals, the product has none, see
+    /// [`SingleRollResult::get_label_totals()`].
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        combine(self, rhs, &MUL)
+    }
+
+    /// Divide two results, `None` if the divisor is zero or if the total overflows.
+    ///
+    /// If the divisor has labelled subtotals, the quotient has none, see
+    /// [`SingleRollResult::get_label_totals()`].
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        combine(self, rhs, &DIV)
+    }
+
+    /// Divide two results exactly, keeping the fraction instead of truncating the total. `None` if
+    /// the divisor is zero or if the total overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::{Rational, Roller};
+    ///
+    /// let seven = Roller::new("7").unwrap().roll().unwrap();
+    /// let two = Roller::new("2").unwrap().roll().unwrap();
+    /// let res = seven
+    ///     .as_single()
+    ///     .unwrap()
+    ///     .clone()
+    ///     .checked_div_exact(two.as_single().unwrap().clone())
+    ///     .unwrap();
+    /// assert_eq!(Rational::new(7, 2), Some(res.get_exact()));
+    /// assert_eq!(3, res.get_total());
+    /// ```
+    pub fn checked_div_exact(mut self, mut rhs: Self) -> Option<Self> {
+        self.exact = Some(self.get_exact());
+        rhs.exact = Some(rhs.get_exact());
+        self.checked_div(rhs)
+    }
+}
+
+/// Saturate to `i64::MIN` or `i64::MAX` on overflow, use [`SingleRollResult::checked_add()`] to
+/// detect it.
 impl std::ops::Add for SingleRollResult {
     type Output = Self;
 
-    fn add(mut self, mut rhs: Self) -> Self::Output {
-        merge_history(&mut self, &mut rhs, " + ");
-        let total = match (self.constant, rhs.constant) {
-            (None, None) => self.total + rhs.total,
-            (None, Some(constant)) => (self.total as f64 + constant).trunc() as i64,
-            (Some(constant), None) => (constant + rhs.total as f64).trunc() as i64,
-            (Some(lconstant), Some(rconstant)) => (lconstant + rconstant).trunc() as i64,
-        };
-        SingleRollResult {
-            total,
-            history: self.history,
-            dirty: false,
-            constant: None,
-        }
+    fn add(self, rhs: Self) -> Self::Output {
+        combine(self, rhs, &SATURATING_ADD).unwrap()
     }
 }
 
+/// Saturate to `i64::MIN` or `i64::MAX` on overflow, use [`SingleRollResult::checked_sub()`] to
+/// detect it.
 impl std::ops::Sub for SingleRollResult {
     type Output = Self;
 
-    fn sub(mut self, mut rhs: Self) -> Self::Output {
-        merge_history(&mut self, &mut rhs, " - ");
-        let total = match (self.constant, rhs.constant) {
-            (None, None) => self.total - rhs.total,
-            (None, Some(constant)) => (self.total as f64 - constant).trunc() as i64,
-            (Some(constant), None) => (constant - rhs.total as f64).trunc() as i64,
-            (Some(lconstant), Some(rconstant)) => (lconstant - rconstant).trunc() as i64,
-        };
-        SingleRollResult {
-            total,
-            history: self.history,
-            dirty: false,
-            constant: None,
-        }
+    fn sub(self, rhs: Self) -> Self::Output {
+        combine(self, rhs, &SATURATING_SUB).unwrap()
     }
 }
 
+/// Saturate to `i64::MIN` or `i64::MAX` on overflow, use [`SingleRollResult::checked_mul()`] to
+/// detect it.
 impl std::ops::Mul for SingleRollResult {
     type Output = Self;
 
-    fn mul(mut self, mut rhs: Self) -> Self::Output {
-        merge_history(&mut self, &mut rhs, " * ");
-        let total = match (self.constant, rhs.constant) {
-            (None, None) => self.total * rhs.total,
-            (None, Some(constant)) => (self.total as f64 * constant).trunc() as i64,
-            (Some(constant), None) => (constant * rhs.total as f64).trunc() as i64,
-            (Some(lconstant), Some(rconstant)) => (lconstant * rconstant).trunc() as i64,
-        };
-        SingleRollResult {
-            total,
-            history: self.history,
-            dirty: false,
-            constant: None,
-        }
+    fn mul(self, rhs: Self) -> Self::Output {
+        combine(self, rhs, &SATURATING_MUL).unwrap()
     }
 }
 
+/// Saturate to `i64::MIN` or `i64::MAX` on overflow. A division by zero gives `i64::MAX`,
+/// `i64::MIN` or 0 following the sign of the dividend. Use [`SingleRollResult::checked_div()`] to
+/// detect both.
 impl std::ops::Div for SingleRollResult {
     type Output = Self;
 
-    fn div(mut self, mut rhs: Self) -> Self::Output {
-        merge_history(&mut self, &mut rhs, " / ");
-        let total = match (self.constant, rhs.constant) {
-            (None, None) => self.total / rhs.total,
-            (None, Some(constant)) => (self.total as f64 / constant).trunc() as i64,
-            (Some(constant), None) => (constant / rhs.total as f64).trunc() as i64,
-            (Some(lconstant), Some(rconstant)) => (lconstant / rconstant).trunc() as i64,
-        };
-        SingleRollResult {
-            total,
-            history: self.history,
-            dirty: false,
-            constant: None,
-        }
+    fn div(self, rhs: Self) -> Self::Output {
+        combine(self, rhs, &SATURATING_DIV).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Roller;
+
+    fn single(input: &str) -> SingleRollResult {
+        Roller::new(input)
+            .unwrap()
+            .roll()
+            .unwrap()
+            .as_single()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn label_operations_test() {
+        // combined outside of the roller, the subtotals are dropped
+        let product = single("1d6[a]").checked_mul(single("1d6[b]")).unwrap();
+        assert!(product.get_label_totals().is_empty());
+        let quotient = single("10").checked_div(single("1d4[fire]")).unwrap();
+        assert!(quotient.get_label_totals().is_empty());
+        let sum = single("1d6[a]") + single("2[a]");
+        assert_eq!(Some(sum.get_total()), sum.get_label_total("a"));
+    }
+
+    #[test]
+    fn saturating_operations_test() {
+        // the operators on results saturate instead of failing
+        let max = single("9223372036854775807");
+        assert!(max.clone().checked_add(single("1")).is_none());
+        assert_eq!(i64::MAX, (max.clone() + single("1")).get_total());
+        assert_eq!(i64::MAX, (max.clone() * single("2")).get_total());
+        assert_eq!(i64::MIN, (single("0 - 2") * max.clone()).get_total());
+        assert_eq!(i64::MAX, (max.clone() + single("0.5")).get_total());
+        assert_eq!(
+            (max.clone() + single("1")).get_total(),
+            (max.clone() + single("0.5") + single("0.5")).get_total()
+        );
+        let min = single("0 - 9223372036854775807 - 1");
+        assert!(min.clone().checked_div(single("0 - 1")).is_none());
+        assert_eq!(i64::MAX, (min.clone() / single("0 - 1")).get_total());
+
+        // a division by zero follows the sign of the dividend
+        let zero = single("0");
+        assert!(single("7").checked_div(zero.clone()).is_none());
+        assert_eq!(i64::MAX, (single("7") / zero.clone()).get_total());
+        assert_eq!(i64::MIN, (single("0 - 7") / zero.clone()).get_total());
+        assert_eq!(0, (single("0") / zero.clone()).get_total());
+        assert_eq!(i64::MAX, (single("1.5") / zero).get_total());
     }
 }
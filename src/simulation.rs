@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{Result, RollError, Span},
+    RollResult, RollResultType, Roller, SeededSource,
+};
+
+/// Options of the simulation of an expression, see [`Roller::simulate()`].
+///
+/// # Examples
+///
+/// ```
+/// use caith::{Roller, SimulationOptions};
+///
+/// let options = SimulationOptions {
+///     seed: 42,
+///     precision: Some(0.05),
+///     ..Default::default()
+/// };
+/// let sim = Roller::new("10d6 ie6 K8").unwrap().simulate(&options).unwrap();
+/// let (low, high) = sim.confidence_interval();
+/// assert!(high - low <= 0.1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationOptions {
+    /// Maximum number of rolls
+    pub iterations: u64,
+    /// Seed of the [`SeededSource`] rolling the dice, the same seed gives the same simulation
+    pub seed: u64,
+    /// Stop before `iterations` once the confidence interval of the mean is at most twice this
+    /// value wide. Checked every 1000 rolls.
+    pub precision: Option<f64>,
+    /// Confidence level of the interval, between 0 and 1 exclusive
+    pub confidence: f64,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        SimulationOptions {
+            iterations: 100_000,
+            seed: 0,
+            precision: None,
+            confidence: 0.95,
+        }
+    }
+}
+
+/// The totals observed while rolling an expression many times, see [`Roller::simulate()`].
+///
+/// # Examples
+///
+/// ```
+/// use caith::{Roller, SimulationOptions};
+///
+/// let sim = Roller::new("2d6")
+///     .unwrap()
+///     .simulate(&SimulationOptions::default())
+///     .unwrap();
+/// assert_eq!(100_000, sim.get_iterations());
+/// assert_eq!(7, sim.percentile(50.0));
+/// assert!((sim.mean() - 7.0).abs() < 0.05);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    histogram: BTreeMap<i64, u64>,
+    iterations: u64,
+    // running mean and sum of squared deviations, with Welford's algorithm
+    mean: f64,
+    m2: f64,
+    confidence: f64,
+}
+
+impl Simulation {
+    fn new(confidence: f64) -> Self {
+        Simulation {
+            histogram: BTreeMap::new(),
+            iterations: 0,
+            mean: 0.0,
+            m2: 0.0,
+            confidence,
+        }
+    }
+
+    fn add(&mut self, total: i64) {
+        *self.histogram.entry(total).or_insert(0) += 1;
+        self.iterations += 1;
+        let delta = total as f64 - self.mean;
+        self.mean += delta / self.iterations as f64;
+        self.m2 += delta * (total as f64 - self.mean);
+    }
+
+    /// Get the number of rolls done
+    pub fn get_iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Get the number of times each total was rolled
+    pub fn get_histogram(&self) -> &BTreeMap<i64, u64> {
+        &self.histogram
+    }
+
+    /// Get the lowest total rolled
+    pub fn get_min(&self) -> i64 {
+        self.histogram.keys().next().copied().unwrap_or(0)
+    }
+
+    /// Get the highest total rolled
+    pub fn get_max(&self) -> i64 {
+        self.histogram.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Get the frequency of `total`
+    pub fn probability(&self, total: i64) -> f64 {
+        self.histogram.get(&total).copied().unwrap_or(0) as f64 / self.iterations as f64
+    }
+
+    /// Get the frequency of `total` or less
+    pub fn at_most(&self, total: i64) -> f64 {
+        self.histogram.range(..=total).map(|(_, &c)| c).sum::<u64>() as f64 / self.iterations as f64
+    }
+
+    /// Get the frequency of `total` or more
+    pub fn at_least(&self, total: i64) -> f64 {
+        self.histogram.range(total..).map(|(_, &c)| c).sum::<u64>() as f64 / self.iterations as f64
+    }
+
+    /// Get the mean of the totals
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Get the sample variance of the totals
+    pub fn variance(&self) -> f64 {
+        if self.iterations > 1 {
+            self.m2 / (self.iterations - 1) as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Get the sample standard deviation of the totals
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Get the lowest total such that at least `percent` % of the totals are lower or equal
+    pub fn percentile(&self, percent: f64) -> i64 {
+        let rank = (percent / 100.0 * self.iterations as f64).ceil().max(1.0) as u64;
+        let mut count = 0;
+        for (&total, &c) in self.histogram.iter() {
+            count += c;
+            if count >= rank {
+                return total;
+            }
+        }
+        self.get_max()
+    }
+
+    /// Get the confidence interval of the mean, at the confidence level of the
+    /// [`SimulationOptions`]
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        let half_width = self.half_width();
+        (self.mean - half_width, self.mean + half_width)
+    }
+
+    fn half_width(&self) -> f64 {
+        let z = normal_quantile(0.5 + self.confidence / 2.0);
+        z * self.std_dev() / (self.iterations as f64).sqrt()
+    }
+}
+
+// Quantile of the standard normal distribution, for 0.5 <= p < 1, with the approximation 26.2.23
+// of Abramowitz and Stegun (error below 4.5e-4)
+fn normal_quantile(p: f64) -> f64 {
+    let t = (-2.0 * (1.0 - p).ln()).sqrt();
+    t - (2.515517 + 0.802853 * t + 0.010328 * t * t)
+        / (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t)
+}
+
+impl Roller {
+    /// Roll the expression many times with a [`SeededSource`] and gather the totals.
+    ///
+    /// The expression is parsed once. A repetition must be summed with `^+` to have a total. The
+    /// options are checked first: a confidence level not between 0 and 1 exclusive gives
+    /// [`RollError::InvalidConfidence`], and 0 iterations gives [`RollError::NoIterations`].
+    pub fn simulate(&self, options: &SimulationOptions) -> Result<Simulation> {
+        let pairs = self.parse()?;
+        let span = pairs.clone().next().unwrap().as_span().into();
+        simulate_totals(options, |source| {
+            single_total(&self.roll_parsed(pairs.clone(), source)?, span)
+        })
+    }
+}
+
+// Gather the totals given by `roll`, according to `options`
+pub(crate) fn simulate_totals<F>(options: &SimulationOptions, mut roll: F) -> Result<Simulation>
+where
+    F: FnMut(&mut SeededSource) -> Result<i64>,
+{
+    if !(options.confidence > 0.0 && options.confidence < 1.0) {
+        return Err(RollError::InvalidConfidence);
+    }
+    if options.iterations == 0 {
+        return Err(RollError::NoIterations);
+    }
+    let mut source = SeededSource::new(options.seed);
+    let mut sim = Simulation::new(options.confidence);
+    for i in 1..=options.iterations {
+        sim.add(roll(&mut source)?);
+        match options.precision {
+            Some(precision) if i % 1000 == 0 && sim.half_width() <= precision => break,
+            _ => (),
+        }
+    }
+    Ok(sim)
+}
+
+// A repetition has a total only if summed with `^+`
+pub(crate) fn single_total(res: &RollResult, span: Span) -> Result<i64> {
+    match res.get_result() {
+        RollResultType::Single(res) => Ok(res.get_total()),
+        RollResultType::Repeated(res) => res.get_total().ok_or(RollError::NoSingleTotal { span }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation_test() {
+        let options = SimulationOptions {
+            iterations: 20_000,
+            seed: 7,
+            ..Default::default()
+        };
+        let roller = Roller::new("3d6 + 2").unwrap();
+        let sim = roller.simulate(&options).unwrap();
+        assert_eq!(sim, roller.simulate(&options).unwrap());
+        assert_eq!(20_000, sim.get_histogram().values().sum::<u64>());
+        assert_eq!((5, 20), (sim.get_min(), sim.get_max()));
+        let (low, high) = sim.confidence_interval();
+        assert!(low < 12.5 && 12.5 < high, "{} {}", low, high);
+        assert!((sim.variance() - 8.75).abs() < 0.3);
+        assert_eq!(5, sim.percentile(0.0));
+        assert_eq!(20, sim.percentile(100.0));
+        assert!((sim.at_least(13) + sim.at_most(12) - 1.0).abs() < 1e-9);
+
+        let sim = Roller::new("(1d6 + 1d6)^+2")
+            .unwrap()
+            .simulate(&options)
+            .unwrap();
+        assert!((sim.mean() - 14.0).abs() < 0.1);
+
+        let options = SimulationOptions {
+            precision: Some(0.1),
+            ..options
+        };
+        let sim = roller.simulate(&options).unwrap();
+        assert!(sim.get_iterations() < 20_000);
+        assert_eq!(0, sim.get_iterations() % 1000);
+
+        assert_eq!(
+            Err(RollError::NoSingleTotal {
+                span: Span::new(0, 7)
+            }),
+            Roller::new("(1d6)^3").unwrap().simulate(&options)
+        );
+        let invalid = SimulationOptions {
+            confidence: 1.0,
+            ..options
+        };
+        assert_eq!(Err(RollError::InvalidConfidence), roller.simulate(&invalid));
+        let invalid = SimulationOptions {
+            iterations: 0,
+            ..options
+        };
+        assert_eq!(Err(RollError::NoIterations), roller.simulate(&invalid));
+    }
+}
@@ -0,0 +1,147 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    error::{Result, RollError},
+    Roller,
+};
+
+const PLACEHOLDER: &str = "{}";
+
+/// An expression with a free integer parameter, written `{}`, like `3d6 + {}`.
+///
+/// # Examples
+///
+/// ```
+/// use caith::RollTemplate;
+///
+/// // what bonus makes 3d6 reach 12 half the time?
+/// let template = RollTemplate::new("3d6 + {}").unwrap();
+/// assert_eq!(1, template.solve(12, 0.5, -5..=5).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollTemplate {
+    template: String,
+}
+
+impl RollTemplate {
+    /// Create a template, which must contain `{}` exactly once
+    pub fn new(template: &str) -> Result<Self> {
+        match template.matches(PLACEHOLDER).count() {
+            1 => Ok(RollTemplate {
+                template: template.to_owned(),
+            }),
+            0 => Err(RollError::InvalidTemplate("missing `{}`".to_owned())),
+            _ => Err(RollError::InvalidTemplate(
+                "`{}` must appear only once".to_owned(),
+            )),
+        }
+    }
+
+    /// Get the template string
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Get the roller of the expression with `value` as parameter
+    pub fn roller(&self, value: i64) -> Result<Roller> {
+        Roller::new(&self.template.replace(PLACEHOLDER, &value.to_string()))
+    }
+
+    /// Find the parameter among `values` for which the probability to reach `dc` is the closest to
+    /// `probability`. The lowest parameter is chosen when several are as close.
+    ///
+    /// Each parameter is analysed with [`Roller::chance_to_reach()`]. A parameter giving an error,
+    /// like `0` for `{}d6`, is skipped: the error of the first one is returned only if no
+    /// parameter can be analysed.
+    pub fn solve(&self, dc: i64, probability: f64, values: RangeInclusive<i64>) -> Result<i64> {
+        let mut best: Option<(i64, f64)> = None;
+        let mut error = None;
+        for value in values {
+            let gap = match self
+                .roller(value)
+                .and_then(|roller| roller.chance_to_reach(dc))
+            {
+                Ok(p) => (p - probability).abs(),
+                Err(e) => {
+                    error.get_or_insert(e);
+                    continue;
+                }
+            };
+            match best {
+                Some((_, best_gap)) if best_gap <= gap => (),
+                _ => best = Some((value, gap)),
+            }
+        }
+        match (best, error) {
+            (Some((value, _)), _) => Ok(value),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(RollError::InvalidTemplate("no value to try".to_owned())),
+        }
+    }
+}
+
+impl Roller {
+    /// Find the difficulty for which the probability to reach it is the closest to `probability`.
+    /// The lowest difficulty is chosen when several are as close.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::Roller;
+    ///
+    /// // what DC gives a 65% success rate?
+    /// let dc = Roller::new("1d20 + 7").unwrap().solve_difficulty(0.65).unwrap();
+    /// assert_eq!(15, dc);
+    /// ```
+    pub fn solve_difficulty(&self, probability: f64) -> Result<i64> {
+        let dist = self.distribution()?;
+        // reaching above `max` is impossible
+        let mut best = (dist.get_max().saturating_add(1), probability.abs());
+        // from the highest difficulty, so the lowest one wins when as close, up to rounding errors
+        for (dc, reach) in dist.survival() {
+            let gap = (reach - probability).abs();
+            if gap <= best.1 + 1e-12 {
+                best = (dc, gap);
+            }
+        }
+        Ok(best.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solver_test() {
+        let roller = Roller::new("2d6").unwrap();
+        assert_eq!(2, roller.solve_difficulty(1.0).unwrap());
+        assert_eq!(13, roller.solve_difficulty(0.0).unwrap());
+        // P(>= 7) = 21/36 and P(>= 8) = 15/36 are as close, the lowest is chosen
+        assert_eq!(7, roller.solve_difficulty(0.5).unwrap());
+        assert_eq!(8, roller.solve_difficulty(0.45).unwrap());
+        // reaching 11 is as likely as reaching 12
+        let roller = Roller::new("1d6 * 2").unwrap();
+        assert_eq!(11, roller.solve_difficulty(1.0 / 6.0).unwrap());
+
+        let template = RollTemplate::new("{}d6 t5").unwrap();
+        assert_eq!("3d6 t5", template.roller(3).unwrap().as_str());
+        // 1 - (2/3)^6 is the closest to 90%
+        assert_eq!(6, template.solve(1, 0.9, 1..=10).unwrap());
+
+        assert_eq!(
+            Err(RollError::InvalidTemplate("missing `{}`".to_owned())),
+            RollTemplate::new("1d6")
+        );
+        assert!(matches!(
+            RollTemplate::new("{}d6 + {}"),
+            Err(RollError::InvalidTemplate(_))
+        ));
+        // `0d6 t5` is invalid and skipped
+        assert_eq!(3, template.solve(1, 0.9, 0..=3).unwrap());
+        assert!(matches!(
+            template.solve(1, 0.9, -2..=0),
+            Err(RollError::Syntax { .. })
+        ));
+    }
+}
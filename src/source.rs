@@ -0,0 +1,457 @@
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::{StdRng, ThreadRng},
+    Rng, SeedableRng,
+};
+
+use crate::error::{Result, RollError, Span};
+
+/// Provide the value of every die rolled during an evaluation.
+///
+/// Implement it to control the dice, see [`crate::Roller::roll_with_source()`]. This crate
+/// provides [`RngDiceRollSource`], [`SeededSource`], [`ScriptedSource`], [`MaxSource`],
+/// [`MinSource`] and [`RecordingSource`].
+///
+/// # Examples
+///
+/// ```
+/// use caith::{DiceRollSource, Roller};
+///
+/// // a very loaded die
+/// struct AlwaysTwo;
+///
+/// impl DiceRollSource for AlwaysTwo {
+///     fn roll_single_die(&mut self, _sides: u64) -> u64 {
+///         2
+///     }
+/// }
+///
+/// let res = Roller::new("3d6").unwrap().roll_with_source(&mut AlwaysTwo).unwrap();
+/// assert_eq!(6, res.as_single().unwrap().get_total());
+/// ```
+pub trait DiceRollSource {
+    /// Roll a die of `sides` sides, returning a value between 1 and `sides`.
+    fn roll_single_die(&mut self, sides: u64) -> u64;
+
+    /// Roll a die of `sides` sides, or fail if the source can't provide a value.
+    ///
+    /// This is the method used during evaluation. The default implementation never fails and calls
+    /// [`DiceRollSource::roll_single_die()`]. The span of a returned error is replaced by the span
+    /// of the dice expression being rolled.
+    fn try_roll_single_die(&mut self, sides: u64) -> Result<u64> {
+        Ok(self.roll_single_die(sides))
+    }
+
+    /// Roll `count` dice of `sides` sides, appending the values to `out`.
+    ///
+    /// This is the method used during evaluation, each dice expression being rolled at once. The
+    /// default implementation calls [`DiceRollSource::try_roll_single_die()`] for each die,
+    /// override it when a batch can be rolled faster.
+    fn roll_many(&mut self, count: u64, sides: u64, out: &mut Vec<u64>) -> Result<()> {
+        for _ in 0..count {
+            out.push(self.try_roll_single_die(sides)?);
+        }
+        Ok(())
+    }
+}
+
+impl<S: DiceRollSource + ?Sized> DiceRollSource for &mut S {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        (**self).roll_single_die(sides)
+    }
+
+    fn try_roll_single_die(&mut self, sides: u64) -> Result<u64> {
+        (**self).try_roll_single_die(sides)
+    }
+
+    fn roll_many(&mut self, count: u64, sides: u64, out: &mut Vec<u64>) -> Result<()> {
+        (**self).roll_many(count, sides, out)
+    }
+}
+
+/// Roll the dice with a random number generator from `rand`.
+///
+/// This is what [`crate::Roller::roll_with()`] uses. The dice are sampled with a
+/// `rand::distributions::Uniform`, set up once for a batch of dice.
+pub struct RngDiceRollSource<'a, T>
+where
+    T: Rng,
+{
+    pub(crate) rng: &'a mut T,
+}
+
+impl<'a, T> RngDiceRollSource<'a, T>
+where
+    T: Rng,
+{
+    /// Use the given random number generator
+    pub fn new(rng: &'a mut T) -> Self {
+        RngDiceRollSource { rng }
+    }
+}
+
+// Sample the dice with a `Uniform` set up once for the batch, which is faster than `gen_range` on
+// big batches. A single die is sampled the same way, so rolling a batch or rolling its dice one by
+// one gives the same values.
+fn uniform_roll_single_die<R: Rng + ?Sized>(rng: &mut R, sides: u64) -> u64 {
+    Uniform::new_inclusive(1, sides).sample(rng)
+}
+
+fn uniform_roll_many<R: Rng + ?Sized>(rng: &mut R, count: u64, sides: u64, out: &mut Vec<u64>) {
+    let dist = Uniform::new_inclusive(1, sides);
+    out.extend(dist.sample_iter(rng).take(count as usize));
+}
+
+impl<T> DiceRollSource for RngDiceRollSource<'_, T>
+where
+    T: Rng,
+{
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        uniform_roll_single_die(self.rng, sides)
+    }
+
+    fn roll_many(&mut self, count: u64, sides: u64, out: &mut Vec<u64>) -> Result<()> {
+        uniform_roll_many(self.rng, count, sides, out);
+        Ok(())
+    }
+}
+
+// The source of `Roller::roll()`
+pub(crate) struct ThreadRngSource {
+    rng: ThreadRng,
+}
+
+impl ThreadRngSource {
+    pub(crate) fn new() -> Self {
+        ThreadRngSource {
+            rng: rand::thread_rng(),
+        }
+    }
+}
+
+impl DiceRollSource for ThreadRngSource {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        uniform_roll_single_die(&mut self.rng, sides)
+    }
+
+    fn roll_many(&mut self, count: u64, sides: u64, out: &mut Vec<u64>) -> Result<()> {
+        uniform_roll_many(&mut self.rng, count, sides, out);
+        Ok(())
+    }
+}
+
+/// Roll the dice with a pseudo random number generator initialized from a seed: the same seed
+/// gives the same dice.
+///
+/// The sequence is reproducible for a given version of this crate and of `rand`, it may change
+/// when either is upgraded. It changed in this version, when the dice started to be sampled in
+/// batch. It's the one of [`crate::Roller::roll_with()`] given a `StdRng` seeded with
+/// `seed_from_u64()`.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{Roller, SeededSource};
+///
+/// let roller = Roller::new("10d20").unwrap();
+/// let first = roller.roll_with_source(&mut SeededSource::new(42)).unwrap();
+/// let second = roller.roll_with_source(&mut SeededSource::new(42)).unwrap();
+/// assert_eq!(first.to_string(), second.to_string());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeededSource {
+    rng: StdRng,
+}
+
+impl SeededSource {
+    /// Initialize the generator from `seed`
+    pub fn new(seed: u64) -> Self {
+        SeededSource {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl DiceRollSource for SeededSource {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        uniform_roll_single_die(&mut self.rng, sides)
+    }
+
+    fn roll_many(&mut self, count: u64, sides: u64, out: &mut Vec<u64>) -> Result<()> {
+        uniform_roll_many(&mut self.rng, count, sides, out);
+        Ok(())
+    }
+}
+
+/// Give the values of a list or of an iterator, in order.
+///
+/// Rolling fails with [`RollError::SourceExhausted`] when there is no more values, and with
+/// [`RollError::InvalidDieValue`] when a value is not between 1 and the number of sides of the die.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{RollError, Roller, ScriptedSource};
+///
+/// let roller = Roller::new("2d6 + 1d20").unwrap();
+/// let res = roller
+///     .roll_with_source(&mut ScriptedSource::new(vec![3, 5, 18]))
+///     .unwrap();
+/// assert_eq!(26, res.as_single().unwrap().get_total());
+///
+/// let e = roller
+///     .roll_with_source(&mut ScriptedSource::from_slice(&[3, 5]))
+///     .unwrap_err();
+/// assert!(matches!(e, RollError::SourceExhausted { rolled: 2, .. }));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScriptedSource<I>
+where
+    I: Iterator<Item = u64>,
+{
+    values: I,
+    rolled: usize,
+}
+
+impl<I> ScriptedSource<I>
+where
+    I: Iterator<Item = u64>,
+{
+    /// Give the values of `values`, in order
+    pub fn new<T: IntoIterator<IntoIter = I>>(values: T) -> Self {
+        ScriptedSource {
+            values: values.into_iter(),
+            rolled: 0,
+        }
+    }
+
+    /// Get the number of values given so far
+    pub fn get_rolled(&self) -> usize {
+        self.rolled
+    }
+}
+
+impl<'a> ScriptedSource<std::iter::Copied<std::slice::Iter<'a, u64>>> {
+    /// Give the values of `values`, in order
+    pub fn from_slice(values: &'a [u64]) -> Self {
+        Self::new(values.iter().copied())
+    }
+}
+
+impl<I> DiceRollSource for ScriptedSource<I>
+where
+    I: Iterator<Item = u64>,
+{
+    /// # Panics
+    ///
+    /// Panics if there is no more values, or if the value is not between 1 and `sides`.
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        match self.try_roll_single_die(sides) {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_roll_single_die(&mut self, sides: u64) -> Result<u64> {
+        let value = self.values.next().ok_or(RollError::SourceExhausted {
+            span: Span::default(),
+            rolled: self.rolled,
+        })?;
+        if value == 0 || value > sides {
+            return Err(RollError::InvalidDieValue {
+                span: Span::default(),
+                value,
+                sides,
+            });
+        }
+        self.rolled += 1;
+        Ok(value)
+    }
+}
+
+/// Every die gives its highest value.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{MaxSource, Roller};
+///
+/// let res = Roller::new("2d6 + 1d8").unwrap().roll_with_source(&mut MaxSource).unwrap();
+/// assert_eq!(20, res.as_single().unwrap().get_total());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MaxSource;
+
+impl DiceRollSource for MaxSource {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        sides
+    }
+}
+
+/// Every die gives 1.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{MinSource, Roller};
+///
+/// let res = Roller::new("2d6 + 1d8").unwrap().roll_with_source(&mut MinSource).unwrap();
+/// assert_eq!(3, res.as_single().unwrap().get_total());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MinSource;
+
+impl DiceRollSource for MinSource {
+    fn roll_single_die(&mut self, _sides: u64) -> u64 {
+        1
+    }
+}
+
+/// A die rolled by a [`RecordingSource`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedRoll {
+    /// Number of sides of the die
+    pub sides: u64,
+    /// Value given by the die
+    pub value: u64,
+}
+
+/// Wrap another source and record every die it rolls, in order.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{RecordedRoll, RecordingSource, Roller, ScriptedSource};
+///
+/// let mut source = RecordingSource::new(ScriptedSource::new(vec![4, 12]));
+/// Roller::new("1d6 + 1d20").unwrap().roll_with_source(&mut source).unwrap();
+/// assert_eq!(
+///     &[
+///         RecordedRoll { sides: 6, value: 4 },
+///         RecordedRoll { sides: 20, value: 12 }
+///     ],
+///     source.get_rolls()
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordingSource<S: DiceRollSource> {
+    inner: S,
+    rolls: Vec<RecordedRoll>,
+}
+
+impl<S: DiceRollSource> RecordingSource<S> {
+    /// Record the dice rolled by `inner`
+    pub fn new(inner: S) -> Self {
+        RecordingSource {
+            inner,
+            rolls: Vec::new(),
+        }
+    }
+
+    /// Get the dice rolled so far, in order
+    pub fn get_rolls(&self) -> &[RecordedRoll] {
+        &self.rolls
+    }
+
+    /// Forget the dice rolled so far
+    pub fn clear(&mut self) {
+        self.rolls.clear();
+    }
+
+    /// Get back the wrapped source and the dice rolled
+    pub fn into_parts(self) -> (S, Vec<RecordedRoll>) {
+        (self.inner, self.rolls)
+    }
+}
+
+impl<S: DiceRollSource> DiceRollSource for RecordingSource<S> {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        let value = self.inner.roll_single_die(sides);
+        self.rolls.push(RecordedRoll { sides, value });
+        value
+    }
+
+    fn try_roll_single_die(&mut self, sides: u64) -> Result<u64> {
+        let value = self.inner.try_roll_single_die(sides)?;
+        self.rolls.push(RecordedRoll { sides, value });
+        Ok(value)
+    }
+
+    fn roll_many(&mut self, count: u64, sides: u64, out: &mut Vec<u64>) -> Result<()> {
+        let start = out.len();
+        let res = self.inner.roll_many(count, sides, out);
+        // the dice given before a failure are recorded too
+        self.rolls.extend(
+            out[start..]
+                .iter()
+                .map(|&value| RecordedRoll { sides, value }),
+        );
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Roller;
+
+    #[test]
+    fn scripted_source_test() {
+        let roller = Roller::new("1d6 + 2d20").unwrap();
+        assert_eq!(
+            Err(RollError::SourceExhausted {
+                span: Span::new(6, 10),
+                rolled: 2
+            }),
+            roller
+                .roll_with_source(&mut ScriptedSource::new(vec![1, 2]))
+                .map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidDieValue {
+                span: Span::new(0, 4),
+                value: 7,
+                sides: 6
+            }),
+            roller
+                .roll_with_source(&mut ScriptedSource::from_slice(&[7]))
+                .map(|_| ())
+        );
+    }
+
+    #[test]
+    fn recording_source_test() {
+        let roller = Roller::new("4d6 r2 + 1d4").unwrap();
+        let mut source = RecordingSource::new(SeededSource::new(7));
+        let res = roller.roll_with_source(&mut source).unwrap();
+        let (_, rolls) = source.into_parts();
+        assert!(rolls.len() >= 5);
+        assert!(rolls.iter().all(|r| r.value >= 1 && r.value <= r.sides));
+
+        // replaying the recorded values gives the same result
+        let mut replay = ScriptedSource::new(rolls.iter().map(|r| r.value));
+        let res2 = roller.roll_with_source(&mut replay).unwrap();
+        assert_eq!(res.to_string(), res2.to_string());
+        assert_eq!(rolls.len(), replay.get_rolled());
+    }
+
+    #[test]
+    fn seeded_source_test() {
+        let mut source = SeededSource::new(42);
+        let mut batch = vec![];
+        source.roll_many(10, 20, &mut batch).unwrap();
+        // pins the sequence of a seed
+        assert_eq!(vec![11, 11, 13, 9, 1, 9, 15, 17, 3, 1], batch);
+
+        let mut source = SeededSource::new(42);
+        let one_by_one: Vec<_> = (0..10).map(|_| source.roll_single_die(20)).collect();
+        assert_eq!(batch, one_by_one);
+
+        // the same as a seeded generator given to `roll_with()`
+        let roller = Roller::new("10d20").unwrap();
+        let seeded = roller.roll_with_source(&mut SeededSource::new(42)).unwrap();
+        let rng = roller.roll_with(&mut StdRng::seed_from_u64(42)).unwrap();
+        assert_eq!(seeded, rng);
+    }
+}
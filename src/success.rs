@@ -0,0 +1,150 @@
+use crate::{
+    error::{Result, RollError},
+    parser::Rule,
+    Roller,
+};
+
+impl Roller {
+    /// Get the probability that the total is at least `dc`, from the exact [`Roller::distribution()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::Roller;
+    ///
+    /// let p = Roller::new("1d20 + 7").unwrap().chance_to_reach(15).unwrap();
+    /// assert!((p - 0.65).abs() < 1e-9);
+    /// ```
+    pub fn chance_to_reach(&self, dc: i64) -> Result<f64> {
+        Ok(self.distribution()?.at_least(dc))
+    }
+
+    /// Get the probability to have at least `successes` successes.
+    ///
+    /// Every dice of the expression must count successes with `t`, `tt` or `f`, constants add
+    /// successes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::Roller;
+    ///
+    /// let p = Roller::new("4d6 t5").unwrap().chance_of_successes(2).unwrap();
+    /// assert!((p - 33.0 / 81.0).abs() < 1e-9);
+    /// ```
+    pub fn chance_of_successes(&self, successes: i64) -> Result<f64> {
+        self.check_success_pool()?;
+        Ok(self.distribution()?.at_least(successes))
+    }
+
+    /// Get the expected number of successes, failures counting negatively.
+    ///
+    /// Every dice of the expression must count successes with `t`, `tt` or `f`, constants add
+    /// successes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use caith::Roller;
+    ///
+    /// let expected = Roller::new("5d10 t8 f1").unwrap().expected_successes().unwrap();
+    /// assert!((expected - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn expected_successes(&self) -> Result<f64> {
+        self.check_success_pool()?;
+        Ok(self.distribution()?.mean())
+    }
+
+    // The total is a number of successes only if every dice counts successes
+    fn check_success_pool(&self) -> Result<()> {
+        let pairs = self.parse()?;
+        let expr = pairs.clone().next().unwrap();
+        let mut dice = pairs.flatten().filter(|pair| pair.as_rule() == Rule::dice);
+        let mut has_dice = false;
+        dice.try_for_each(|dice| {
+            has_dice = true;
+            let is_counting = dice.clone().into_inner().any(|option| {
+                matches!(
+                    option.as_rule(),
+                    Rule::target | Rule::double_target | Rule::failure
+                )
+            });
+            if is_counting {
+                Ok(())
+            } else {
+                Err(RollError::NotSuccessCount {
+                    span: dice.as_span().into(),
+                })
+            }
+        })?;
+        if has_dice {
+            Ok(())
+        } else {
+            Err(RollError::NotSuccessCount {
+                span: expr.as_span().into(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Span;
+
+    #[test]
+    fn success_test() {
+        let roller = Roller::new("4d6 t4 tt6 f1").unwrap();
+        // per die: 1/6 * 2 + 2/6 * 1 - 1/6
+        assert!((roller.expected_successes().unwrap() - 4.0 * 0.5).abs() < 1e-9);
+        assert!((roller.chance_of_successes(-4).unwrap() - 1.0).abs() < 1e-9);
+        assert!((roller.chance_of_successes(8).unwrap() - 1.0 / 1296.0).abs() < 1e-9);
+
+        let roller = Roller::new("(3d6 t5 + 1)^+2").unwrap();
+        assert!((roller.expected_successes().unwrap() - 4.0).abs() < 1e-9);
+
+        let roller = Roller::new("2d6 + 3").unwrap();
+        assert!((roller.chance_to_reach(15).unwrap() - 1.0 / 36.0).abs() < 1e-9);
+        assert!((roller.chance_to_reach(5).unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(
+            Err(RollError::NotSuccessCount {
+                span: Span::new(0, 4)
+            }),
+            roller.chance_of_successes(1)
+        );
+        assert_eq!(
+            Err(RollError::NotSuccessCount {
+                span: Span::new(9, 12)
+            }),
+            Roller::new("4d6 t5 + 1d6").unwrap().expected_successes()
+        );
+        assert_eq!(
+            Err(RollError::NotSuccessCount {
+                span: Span::new(0, 1)
+            }),
+            Roller::new("3").unwrap().expected_successes()
+        );
+
+        // too many sequences of dice to enumerate, given up before enumerating them
+        assert_eq!(
+            Err(RollError::TooComplex {
+                span: Span::new(0, 11)
+            }),
+            Roller::new("7d10 e10 r1").unwrap().chance_to_reach(40)
+        );
+        assert_eq!(
+            Err(RollError::TooComplex {
+                span: Span::new(0, 14)
+            }),
+            Roller::new("7d10 e10 r1 t8")
+                .unwrap()
+                .chance_of_successes(3)
+        );
+        assert_eq!(
+            Err(RollError::TooComplex {
+                span: Span::new(0, 14)
+            }),
+            Roller::new("6d10 e10 r1 t8").unwrap().expected_successes()
+        );
+    }
+}
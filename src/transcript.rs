@@ -0,0 +1,412 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    error::{Result, RollError, Span},
+    Arithmetic, DiceRollSource, FloatRounding, RecordedRoll, RecordingSource, RollResult, Roller,
+    RollerLimits, ThreadRngSource,
+};
+
+const ROLLS_SEPARATOR: char = ';';
+
+const ROUNDINGS: [(FloatRounding, &str); 4] = [
+    (FloatRounding::Truncate, "truncate"),
+    (FloatRounding::Floor, "floor"),
+    (FloatRounding::Ceil, "ceil"),
+    (FloatRounding::Nearest, "nearest"),
+];
+
+/// A compact record of a roll: the expression, the limits and the arithmetic of the roller, and
+/// every die rolled with its number of sides, in order.
+///
+/// It's produced by [`Roller::roll_with_transcript()`] and gives back the identical
+/// [`RollResult`] with [`Roller::replay()`].
+///
+/// It can be stored as a string: `6:3 6:5 20:18;2d6 + 1d20 : attack` is the list of dice as
+/// `sides:value`, separated by spaces, then `;` and the expression. The limits and the arithmetic
+/// that differ from the default ones come first, as `name=value`, like
+/// `exact_division=true rounding=nearest max_dice=100 6:3;1d6 / 4`.
+///
+/// # Examples
+///
+/// ```
+/// use caith::{RollTranscript, Roller};
+///
+/// let (res, transcript) = Roller::new("4d6 K3 : strength")
+///     .unwrap()
+///     .roll_with_transcript()
+///     .unwrap();
+/// let stored = transcript.to_string();
+///
+/// let transcript: RollTranscript = stored.parse().unwrap();
+/// let replayed = Roller::replay(&transcript).unwrap();
+/// assert_eq!(res, replayed);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RollTranscript {
+    expression: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    limits: RollerLimits,
+    #[cfg_attr(feature = "serde", serde(default))]
+    arithmetic: Arithmetic,
+    rolls: Vec<RecordedRoll>,
+}
+
+impl RollTranscript {
+    /// Create a transcript from an expression and the dice it rolled, in order, with the default
+    /// limits and arithmetic
+    pub fn new(expression: &str, rolls: Vec<RecordedRoll>) -> Self {
+        RollTranscript {
+            expression: expression.to_owned(),
+            limits: RollerLimits::default(),
+            arithmetic: Arithmetic::default(),
+            rolls,
+        }
+    }
+
+    /// Set the limits the expression was rolled with
+    pub fn with_limits(mut self, limits: RollerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the arithmetic the expression was rolled with
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    /// Get the rolled expression
+    pub fn get_expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Get the limits the expression was rolled with
+    pub fn get_limits(&self) -> &RollerLimits {
+        &self.limits
+    }
+
+    /// Get the arithmetic the expression was rolled with
+    pub fn get_arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// Get the dice rolled, in order
+    pub fn get_rolls(&self) -> &[RecordedRoll] {
+        &self.rolls
+    }
+}
+
+// The limits and the arithmetic as `name=value`, the ones equal to the default being left out
+fn settings(limits: &RollerLimits, arithmetic: &Arithmetic) -> Vec<String> {
+    let default_limits = RollerLimits::default();
+    let default_arithmetic = Arithmetic::default();
+    let mut settings = Vec::new();
+    if arithmetic.exact_division != default_arithmetic.exact_division {
+        settings.push(format!("exact_division={}", arithmetic.exact_division));
+    }
+    if arithmetic.rounding != default_arithmetic.rounding {
+        let (_, name) = ROUNDINGS
+            .iter()
+            .find(|(rounding, _)| *rounding == arithmetic.rounding)
+            .unwrap();
+        settings.push(format!("rounding={}", name));
+    }
+    let limits = [
+        ("max_dice", limits.max_dice, default_limits.max_dice),
+        ("max_sides", limits.max_sides, default_limits.max_sides),
+        (
+            "max_explosions",
+            limits.max_explosions,
+            default_limits.max_explosions,
+        ),
+        (
+            "max_rerolls",
+            limits.max_rerolls,
+            default_limits.max_rerolls,
+        ),
+        (
+            "max_repetitions",
+            limits.max_repetitions,
+            default_limits.max_repetitions,
+        ),
+        (
+            "max_total_dice",
+            limits.max_total_dice,
+            default_limits.max_total_dice,
+        ),
+        (
+            "max_depth",
+            limits.max_depth as u64,
+            default_limits.max_depth as u64,
+        ),
+    ];
+    for (name, value, default) in limits.iter() {
+        if value != default {
+            settings.push(format!("{}={}", name, value));
+        }
+    }
+    settings
+}
+
+fn parse_setting(
+    name: &str,
+    value: &str,
+    limits: &mut RollerLimits,
+    arithmetic: &mut Arithmetic,
+) -> Option<()> {
+    match name {
+        "exact_division" => arithmetic.exact_division = value.parse().ok()?,
+        "rounding" => {
+            arithmetic.rounding = ROUNDINGS
+                .iter()
+                .find(|(_, n)| *n == value)
+                .map(|(rounding, _)| *rounding)?
+        }
+        "max_dice" => limits.max_dice = value.parse().ok()?,
+        "max_sides" => limits.max_sides = value.parse().ok()?,
+        "max_explosions" => limits.max_explosions = value.parse().ok()?,
+        "max_rerolls" => limits.max_rerolls = value.parse().ok()?,
+        "max_repetitions" => limits.max_repetitions = value.parse().ok()?,
+        "max_total_dice" => limits.max_total_dice = value.parse().ok()?,
+        "max_depth" => limits.max_depth = value.parse().ok()?,
+        _ => return None,
+    }
+    Some(())
+}
+
+impl Display for RollTranscript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rolls = settings(&self.limits, &self.arithmetic)
+            .into_iter()
+            .chain(
+                self.rolls
+                    .iter()
+                    .map(|r| format!("{}:{}", r.sides, r.value)),
+            )
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "{}{}{}", rolls, ROLLS_SEPARATOR, self.expression)
+    }
+}
+
+impl FromStr for RollTranscript {
+    type Err = RollError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (rolls, expression) = s.split_once(ROLLS_SEPARATOR).ok_or_else(|| {
+            RollError::InvalidTranscript(format!("missing `{}`", ROLLS_SEPARATOR))
+        })?;
+        let mut limits = RollerLimits::default();
+        let mut arithmetic = Arithmetic::default();
+        let mut items = rolls.split_whitespace().peekable();
+        while let Some((name, value)) = items.peek().and_then(|item| item.split_once('=')) {
+            parse_setting(name, value, &mut limits, &mut arithmetic).ok_or_else(|| {
+                RollError::InvalidTranscript(format!(
+                    "`{}` is not a valid setting",
+                    items.peek().unwrap()
+                ))
+            })?;
+            items.next();
+        }
+        let rolls = items
+            .map(|roll| {
+                let parsed = roll
+                    .split_once(':')
+                    .and_then(|(sides, value)| Some((sides.parse().ok()?, value.parse().ok()?)));
+                match parsed {
+                    Some((sides, value)) => Ok(RecordedRoll { sides, value }),
+                    None => Err(RollError::InvalidTranscript(format!(
+                        "`{}` is not a `sides:value` die",
+                        roll
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RollTranscript::new(expression, rolls)
+            .with_limits(limits)
+            .with_arithmetic(arithmetic))
+    }
+}
+
+// Give back the recorded dice, checking they are requested in the same order
+struct ReplaySource<'a> {
+    rolls: &'a [RecordedRoll],
+    index: usize,
+}
+
+impl DiceRollSource for ReplaySource<'_> {
+    fn roll_single_die(&mut self, sides: u64) -> u64 {
+        match self.try_roll_single_die(sides) {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_roll_single_die(&mut self, sides: u64) -> Result<u64> {
+        let recorded = self.rolls.get(self.index);
+        match recorded {
+            Some(r) if r.sides == sides => {
+                if r.value == 0 || r.value > sides {
+                    return Err(RollError::InvalidDieValue {
+                        span: Span::default(),
+                        value: r.value,
+                        sides,
+                    });
+                }
+                self.index += 1;
+                Ok(r.value)
+            }
+            _ => Err(RollError::ReplayMismatch {
+                span: Span::default(),
+                index: self.index,
+                requested_sides: sides,
+                recorded_sides: recorded.map(|r| r.sides),
+            }),
+        }
+    }
+}
+
+impl Roller {
+    /// Evaluate and roll the dice with default Rng source (`rand::thread_rng()`), and record a
+    /// transcript of the roll.
+    pub fn roll_with_transcript(&self) -> Result<(RollResult, RollTranscript)> {
+        self.roll_with_source_transcript(&mut ThreadRngSource::new())
+    }
+
+    /// Evaluate and roll the dice with provided dice roll source, and record a transcript of the
+    /// roll.
+    pub fn roll_with_source_transcript<RNG: DiceRollSource>(
+        &self,
+        rng: &mut RNG,
+    ) -> Result<(RollResult, RollTranscript)> {
+        let mut source = RecordingSource::new(rng);
+        let res = self.roll_with_source(&mut source)?;
+        let (_, rolls) = source.into_parts();
+        let transcript = RollTranscript::new(self.as_str(), rolls)
+            .with_limits(*self.get_limits())
+            .with_arithmetic(self.get_arithmetic());
+        Ok((res, transcript))
+    }
+
+    /// Evaluate the expression of the transcript again with its dice, its limits and its
+    /// arithmetic.
+    ///
+    /// Fails with [`RollError::ReplayMismatch`] if the expression doesn't roll the same dice as the
+    /// transcript, and with [`RollError::ReplayUnused`] if some dice of the transcript are left.
+    pub fn replay(transcript: &RollTranscript) -> Result<RollResult> {
+        let mut source = ReplaySource {
+            rolls: &transcript.rolls,
+            index: 0,
+        };
+        let res = Roller::new(&transcript.expression)?
+            .with_limits(transcript.limits)
+            .with_arithmetic(transcript.arithmetic)
+            .roll_with_source(&mut source)?;
+        if source.index < transcript.rolls.len() {
+            Err(RollError::ReplayUnused {
+                used: source.index,
+                recorded: transcript.rolls.len(),
+            })
+        } else {
+            Ok(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScriptedSource, SeededSource};
+
+    #[test]
+    fn transcript_test() {
+        let roller = Roller::new("(4d6 K3 + 1d4 ie4)^3 : [a, b, c]").unwrap();
+        let (res, transcript) = roller
+            .roll_with_source_transcript(&mut SeededSource::new(3))
+            .unwrap();
+        let stored = transcript.to_string();
+        let transcript = stored.parse::<RollTranscript>().unwrap();
+        assert_eq!(
+            "(4d6 K3 + 1d4 ie4)^3 : [a, b, c]",
+            transcript.get_expression()
+        );
+        let replayed = Roller::replay(&transcript).unwrap();
+        assert_eq!(res, replayed);
+        let labels = replayed
+            .as_repeated()
+            .unwrap()
+            .iter()
+            .map(|r| r.get_label().unwrap().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a", "b", "c"], labels);
+
+        let transcript: RollTranscript = "6:3 6:5;2d6 + 1d4 : a;b".parse().unwrap();
+        assert_eq!("2d6 + 1d4 : a;b", transcript.get_expression());
+        assert_eq!(
+            Err(RollError::ReplayMismatch {
+                span: Span::new(6, 10),
+                index: 2,
+                requested_sides: 4,
+                recorded_sides: None
+            }),
+            Roller::replay(&transcript).map(|_| ())
+        );
+        let transcript: RollTranscript = "6:3 8:5;2d6".parse().unwrap();
+        assert!(matches!(
+            Roller::replay(&transcript),
+            Err(RollError::ReplayMismatch {
+                index: 1,
+                requested_sides: 6,
+                recorded_sides: Some(8),
+                ..
+            })
+        ));
+        let transcript: RollTranscript = "6:3 6:5 6:1;2d6".parse().unwrap();
+        assert_eq!(
+            Err(RollError::ReplayUnused {
+                used: 2,
+                recorded: 3
+            }),
+            Roller::replay(&transcript).map(|_| ())
+        );
+        assert!("6:3 6:a;2d6".parse::<RollTranscript>().is_err());
+        assert!("2d6".parse::<RollTranscript>().is_err());
+        assert!("rounding=up 6:3;1d6".parse::<RollTranscript>().is_err());
+
+        // the arithmetic and the limits are replayed as they were rolled
+        let exact = Arithmetic {
+            exact_division: true,
+            rounding: FloatRounding::Nearest,
+        };
+        let limits = RollerLimits {
+            max_dice: 10,
+            ..Default::default()
+        };
+        let roller = Roller::new("(1d6 / 4) * 4 + 1d6 / 4")
+            .unwrap()
+            .with_arithmetic(exact)
+            .with_limits(limits);
+        let (res, transcript) = roller
+            .roll_with_source_transcript(&mut ScriptedSource::new(vec![5, 5]))
+            .unwrap();
+        assert_eq!(6, res.as_single().unwrap().get_total());
+        let stored = transcript.to_string();
+        assert_eq!(
+            "exact_division=true rounding=nearest max_dice=10 6:5 6:5;(1d6 / 4) * 4 + 1d6 / 4",
+            stored
+        );
+        let transcript = stored.parse::<RollTranscript>().unwrap();
+        assert_eq!(&exact, &transcript.get_arithmetic());
+        assert_eq!(&limits, transcript.get_limits());
+        assert_eq!(res, Roller::replay(&transcript).unwrap());
+        let transcript = RollTranscript::new(
+            transcript.get_expression(),
+            vec![
+                RecordedRoll { sides: 6, value: 5 },
+                RecordedRoll { sides: 6, value: 5 },
+            ],
+        );
+        assert_ne!(res, Roller::replay(&transcript).unwrap());
+    }
+}